@@ -1,5 +1,31 @@
 pub mod analyzer;
 pub mod audio_stream;
+pub mod band_spectrum;
+pub mod beat_tracker;
+pub mod biquad;
+pub mod chroma;
+pub mod drum_classifier;
+pub mod envelope;
+pub mod file_source;
+pub mod hpss;
+pub mod level_stats;
+pub mod loudness_meter;
+pub mod mel;
+pub mod onset;
+pub mod pitch;
+pub mod pre_filter;
+pub mod ring_buffer;
+pub mod signal_generator;
+pub mod source;
+pub mod stft;
 
-pub use analyzer::{AudioAnalyzer, AudioMetrics};
-pub use audio_stream::AudioStream;
+pub use analyzer::{AudioAnalyzer, AudioMetrics, BandEnergy};
+pub use beat_tracker::Tempo;
+pub use chroma::KeyEstimate;
+pub use drum_classifier::Drum;
+pub use envelope::{Envelope, LevelEnvelopes};
+pub use file_source::FileSource;
+pub use loudness_meter::MeterReading;
+pub use pitch::Pitch;
+pub use signal_generator::{GeneratorSource, SignalKind, SignalSettings};
+pub use source::{AudioSource, SourceKind};
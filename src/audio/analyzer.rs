@@ -1,18 +1,143 @@
-use rustfft::{FftPlanner, num_complex::Complex};
+use std::ops::Range;
 use std::sync::{Arc, RwLock};
+use std::thread::Thread;
 
-use crate::config::AudioConfig;
+use super::band_spectrum::BandSpectrum;
+use super::beat_tracker::{Beat, BeatTracker, Tempo};
+use super::chroma::{self, KeyEstimate, KeyEstimator};
+use super::drum_classifier::{DrumClassifier, DrumHit};
+use super::envelope::{LevelEnvelopes, LevelFollowers, Levels};
+use super::hpss::{Hpss, HpssParams};
+use super::level_stats::{LevelStats, RelativeLevels};
+use super::loudness_meter::{LoudnessMeter, MeterReading};
+use super::mel::{self, MelFilterbank};
+use super::onset::{Onset, OnsetDetector, OnsetParams};
+use super::pitch::{Pitch, PitchParams, PitchTracker};
+use super::pre_filter::PreFilter;
+use super::ring_buffer::{SampleConsumer, SampleProducer};
+use super::stft::Stft;
+use crate::config::{AudioConfig, ChannelMode};
+
+/// Sample rate assumed until a stream reports its negotiated config.
+const DEFAULT_SAMPLE_RATE: f32 = 44100.0;
+
+/// Share of the spectrum's power below the spectral rolloff frequency.
+const ROLLOFF_SHARE: f32 = 0.85;
+
+/// Capacity of the queue between the audio callback and the analyzer.
+/// About 1.3 s of 48 kHz stereo, far more than one hop.
+const RING_CAPACITY: usize = 1 << 17;
+
+/// Energy of one configured band for the current frame.
+#[derive(Clone, Default, Debug)]
+pub struct BandEnergy {
+    pub name: String,
+    /// Smoothed share of the total spectrum energy, 0-1.
+    pub energy: f32,
+}
 
 #[derive(Clone, Default, Debug)]
 pub struct AudioMetrics {
+    /// Stream time in seconds at the end of the analyzed window.
+    pub time: f64,
+    /// RMS level times the loudness multiplier, 0-1, for driving visuals.
     pub loudness: f32,
+    /// Calibrated loudness and peak levels for metering.
+    pub meter: MeterReading,
+    /// Sum of the energies of the bands marked as bass, 0-1.
     pub bass_energy: f32,
+    /// Energy of every configured band, in config order.
+    pub band_energies: Vec<BandEnergy>,
+    /// Left/right level balance: -1 = hard left, 0 = centered, 1 = hard right.
+    pub stereo_balance: f32,
+    /// Phase correlation between channels: 1 = mono, 0 = uncorrelated, -1 = out of phase.
+    pub phase_correlation: f32,
+    /// Side-to-total energy ratio: 0 = mono, higher = wider stereo image.
+    pub stereo_width: f32,
+    /// Band levels of the frame's spectrum on the configured frequency scale.
+    pub spectrum: BandSpectrum,
+    /// Magnitude-weighted mean frequency of the spectrum in Hz, 0 when silent.
+    /// Higher = brighter sound.
+    pub spectral_centroid: f32,
+    /// Magnitude-weighted standard deviation around the centroid in Hz.
+    /// Higher = energy spread over a wider range.
+    pub spectral_spread: f32,
+    /// Frequency below which 85% of the spectrum's power lies, in Hz.
+    pub spectral_rolloff: f32,
+    /// Geometric over arithmetic mean of the power spectrum: close to 1 for
+    /// noise, close to 0 for tones.
+    pub spectral_flatness: f32,
+    /// Share of consecutive samples in the window that change sign, 0-1.
+    /// Higher = noisier or brighter.
+    pub zero_crossing_rate: f32,
+    /// Peak over RMS level of the window: about 1.4 for a sine, higher for
+    /// punchy transients, 0 when silent.
+    pub crest_factor: f32,
+    /// Power per mel band of the frame in dB, low to high.
+    pub mel_energies: Vec<f32>,
+    /// Mel-frequency cepstral coefficients of the frame, from the mel bands.
+    pub mfcc: Vec<f32>,
+    /// Shares of the frame's power in its sustained and its transient part,
+    /// adding up to 1. Both 0 while source separation is off.
+    pub harmonic_energy: f32,
+    pub percussive_energy: f32,
+    /// Full-spectrum spectral flux of the frame (the onset envelope).
+    pub onset_strength: f32,
+    /// Onsets detected in this frame, overall and per energy band.
+    pub onsets: Vec<Onset>,
+    /// Percussion hits classified in this frame.
+    pub drum_hits: Vec<DrumHit>,
+    /// Tempo and beat phase tracked from the onset envelope.
+    pub tempo: Tempo,
+    /// Set on the frame in which a predicted beat falls.
+    pub beat: Option<Beat>,
+    /// Fundamental frequency of the lead line, if the frame is pitched.
+    pub pitch: Pitch,
+    /// Energy per pitch class, C first, scaled so the strongest is 1.
+    pub chroma: [f32; 12],
+    /// Key of the recent music, `None` until something tonal was heard.
+    pub key: Option<KeyEstimate>,
+    /// Loudness and energies ranked against their recent history.
+    pub relative: RelativeLevels,
+    /// Attack/release envelopes of the raw level metrics above.
+    pub envelopes: LevelEnvelopes,
 }
 
 pub struct AudioAnalyzer {
     config: Arc<RwLock<AudioConfig>>,
     buffer: Vec<f32>,
-    fft_planner: FftPlanner<f32>,
+    left_buffer: Vec<f32>,
+    right_buffer: Vec<f32>,
+    /// Left/right as they arrived, before the pre-filter chain. The loudness
+    /// meter reads these, so analysis EQ never shifts the reported levels.
+    unfiltered_left: Vec<f32>,
+    unfiltered_right: Vec<f32>,
+    pre_filter: PreFilter,
+    /// Incoming samples after the pre-filter chain.
+    filtered: Vec<f32>,
+    stft: Stft,
+    mel_filterbank: MelFilterbank,
+    hpss: Hpss,
+    /// Smoothed energy per configured band, carried between hops.
+    band_state: Vec<f32>,
+    /// Names of the bands the per-band state was built for.
+    band_names: Vec<String>,
+    onset_detector: OnsetDetector,
+    drum_classifier: DrumClassifier,
+    beat_tracker: BeatTracker,
+    key_estimator: KeyEstimator,
+    pitch_tracker: PitchTracker,
+    level_stats: LevelStats,
+    envelope_followers: LevelFollowers,
+    loudness_meter: LoudnessMeter,
+    consumer: SampleConsumer,
+    incoming: Vec<f32>,
+    sample_rate: f32,
+    channels: usize,
+    /// Samples at the end of the buffers not yet covered by an analysis hop.
+    unanalyzed: usize,
+    /// Stream position, in frames, of the end of the last analyzed window.
+    frames_analyzed: u64,
 }
 
 impl AudioAnalyzer {
@@ -20,99 +145,701 @@ impl AudioAnalyzer {
         let buffer_size = config.read().unwrap().buffer_size;
         Self {
             buffer: Vec::with_capacity(buffer_size),
-            fft_planner: FftPlanner::new(),
+            left_buffer: Vec::with_capacity(buffer_size),
+            right_buffer: Vec::with_capacity(buffer_size),
+            unfiltered_left: Vec::with_capacity(buffer_size),
+            unfiltered_right: Vec::with_capacity(buffer_size),
+            pre_filter: PreFilter::new(),
+            filtered: Vec::new(),
+            stft: Stft::new(),
+            mel_filterbank: MelFilterbank::new(),
+            hpss: Hpss::new(),
+            band_state: Vec::new(),
+            band_names: Vec::new(),
+            onset_detector: OnsetDetector::new(),
+            drum_classifier: DrumClassifier::new(),
+            beat_tracker: BeatTracker::new(),
+            key_estimator: KeyEstimator::new(),
+            pitch_tracker: PitchTracker::new(),
+            level_stats: LevelStats::new(),
+            envelope_followers: LevelFollowers::default(),
+            loudness_meter: LoudnessMeter::new(DEFAULT_SAMPLE_RATE, 1),
+            consumer: SampleConsumer::new(RING_CAPACITY),
+            incoming: Vec::with_capacity(RING_CAPACITY),
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            channels: 1,
+            unanalyzed: 0,
+            frames_analyzed: 0,
             config,
         }
     }
 
+    /// Adopts the sample rate and channel layout negotiated with the input
+    /// device. Buffered and queued audio in the old format is discarded, so
+    /// the previous stream must already be stopped.
+    pub fn set_stream_config(&mut self, stream_config: &cpal::StreamConfig) {
+        self.set_format(stream_config.sample_rate.0, stream_config.channels as usize);
+    }
+
+    /// Same as [`Self::set_stream_config`] for sources that aren't cpal streams.
+    pub fn set_format(&mut self, sample_rate: u32, channels: usize) {
+        self.sample_rate = sample_rate as f32;
+        self.channels = channels.max(1);
+        self.consumer.clear();
+        self.buffer.clear();
+        self.left_buffer.clear();
+        self.right_buffer.clear();
+        self.unfiltered_left.clear();
+        self.unfiltered_right.clear();
+        self.pre_filter.reset();
+        self.band_state.clear();
+        self.hpss.reset();
+        self.onset_detector.reset();
+        self.drum_classifier.reset();
+        self.beat_tracker.reset();
+        self.key_estimator.reset();
+        self.level_stats.reset();
+        self.envelope_followers.reset();
+        self.loudness_meter = LoudnessMeter::new(self.sample_rate, self.channels);
+        self.unanalyzed = 0;
+        self.frames_analyzed = 0;
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Writing end of the sample queue for a new audio stream, in the format
+    /// last set.
+    pub fn sample_producer(&self) -> SampleProducer {
+        self.consumer.producer(self.channels)
+    }
+
+    /// Unparks `thread` whenever a source has queued enough samples for the
+    /// next hop.
+    pub fn wake_on_samples(&self, thread: Thread) {
+        self.consumer.set_waiter(thread);
+    }
+
+    /// Total frames dropped because the analyzer fell behind the audio callback.
+    pub fn overrun_count(&self) -> usize {
+        self.consumer.overrun_count()
+    }
+
+    /// Drains everything the audio callback queued since the last call.
+    pub fn pull_samples(&mut self) {
+        let mut incoming = std::mem::take(&mut self.incoming);
+        incoming.clear();
+        if self.consumer.pop_into(&mut incoming) > 0 {
+            self.add_samples(&incoming);
+        }
+        self.incoming = incoming;
+    }
+
+    /// Accepts interleaved samples in the current stream layout. They pass
+    /// through the configured pre-filter chain first. The first two
+    /// channels are kept as separate left/right buffers and all channels are
+    /// downmixed to mono according to the configured channel mode.
+    ///
+    /// Samples are only queued here; [`Self::next_hop`] analyzes them.
     pub fn add_samples(&mut self, samples: &[f32]) {
-        self.buffer.extend_from_slice(samples);
+        let mut filtered = std::mem::take(&mut self.filtered);
+        filtered.clear();
+        filtered.extend_from_slice(samples);
+        {
+            let config = self.config.read().unwrap();
+            self.pre_filter.process(
+                &mut filtered,
+                self.channels,
+                self.sample_rate,
+                &config.pre_filters,
+                config.pre_gain_db,
+            );
+        }
+        self.queue_samples(samples, &filtered);
+        self.filtered = filtered;
+    }
 
-        // Keep only the most recent samples
-        let buffer_size = self.config.read().unwrap().buffer_size;
-        if self.buffer.len() > buffer_size {
-            self.buffer.drain(0..self.buffer.len() - buffer_size);
+    fn queue_samples(&mut self, unfiltered: &[f32], samples: &[f32]) {
+        let frames_before = self.buffer.len();
+        if self.channels == 1 {
+            self.buffer.extend_from_slice(samples);
+            self.left_buffer.extend_from_slice(samples);
+            self.right_buffer.extend_from_slice(samples);
+            self.unfiltered_left.extend_from_slice(unfiltered);
+            self.unfiltered_right.extend_from_slice(unfiltered);
+        } else {
+            let channel_mode = self.config.read().unwrap().channel_mode;
+            for (frame, unfiltered) in samples
+                .chunks_exact(self.channels)
+                .zip(unfiltered.chunks_exact(self.channels))
+            {
+                self.buffer.push(downmix(frame, channel_mode));
+                self.left_buffer.push(frame[0]);
+                self.right_buffer.push(frame[1]);
+                self.unfiltered_left.push(unfiltered[0]);
+                self.unfiltered_right.push(unfiltered[1]);
+            }
+        }
+
+        self.unanalyzed += self.buffer.len() - frames_before;
+
+        // Skip audio nobody analyzed in time rather than growing without bound
+        let max_backlog = self.sample_rate as usize;
+        if self.unanalyzed > max_backlog {
+            self.frames_analyzed += (self.unanalyzed - max_backlog) as u64;
+            self.unanalyzed = max_backlog;
+        }
+
+        // Keep one analysis window of history plus everything not yet analyzed
+        let keep = self.config.read().unwrap().buffer_size + self.unanalyzed;
+        for buffer in [
+            &mut self.buffer,
+            &mut self.left_buffer,
+            &mut self.right_buffer,
+            &mut self.unfiltered_left,
+            &mut self.unfiltered_right,
+        ] {
+            if buffer.len() > keep {
+                buffer.drain(0..buffer.len() - keep);
+            }
         }
     }
 
-    pub fn calculate_loudness(&self) -> f32 {
-        if self.buffer.is_empty() {
+    pub fn calculate_loudness(&self, window: Range<usize>) -> f32 {
+        let samples = &self.buffer[window];
+        if samples.is_empty() {
             return 0.0;
         }
 
         // RMS (Root Mean Square) for loudness
-        let sum_squares: f32 = self.buffer.iter().map(|&x| x * x).sum();
-        let rms = (sum_squares / self.buffer.len() as f32).sqrt();
+        let sum_squares: f32 = samples.iter().map(|&x| x * x).sum();
+        let rms = (sum_squares / samples.len() as f32).sqrt();
 
         // Convert to 0-1 scale
         let loudness_multiplier = self.config.read().unwrap().loudness_multiplier;
         (rms * loudness_multiplier).min(1.0)
     }
 
-    pub fn calculate_bass_energy(&mut self) -> f32 {
+    /// Feeds new audio to the loudness meter and returns its reading. The
+    /// meter hears the input before the pre-filter chain.
+    pub fn measure_loudness(&mut self, hop: Range<usize>) -> MeterReading {
+        let left = &self.unfiltered_left[hop.clone()];
+        let right = &self.unfiltered_right[hop];
+        self.loudness_meter.process(&[left, right])
+    }
+
+    /// Each configured band's smoothed share of the current frame's spectrum.
+    pub fn calculate_band_energies(&mut self) -> Vec<BandEnergy> {
+        let config = self.config.read().unwrap();
+        let spectrum = self.stft.spectrum();
+        let total_energy = spectrum.band_sum(0.0, self.sample_rate / 2.0);
+
+        self.band_state.resize(config.energy_bands.len(), 0.0);
+        config
+            .energy_bands
+            .iter()
+            .zip(&mut self.band_state)
+            .map(|(band, state)| {
+                let raw = if total_energy > 0.0 {
+                    let normalized = spectrum.band_sum(band.min_freq, band.max_freq) / total_energy;
+                    (normalized * band.multiplier).min(1.0)
+                } else {
+                    0.0
+                };
+
+                let smoothing = band.smoothing.clamp(0.0, 0.99);
+                *state = *state * smoothing + raw * (1.0 - smoothing);
+
+                BandEnergy {
+                    name: band.name.clone(),
+                    energy: *state,
+                }
+            })
+            .collect()
+    }
+
+    /// Combined energy of the bands marked as bass.
+    fn bass_energy(&self, band_energies: &[BandEnergy]) -> f32 {
         let config = self.config.read().unwrap();
-        let buffer_size = config.buffer_size;
-        let sample_rate = config.sample_rate;
-        let bass_freq_max = config.bass_freq_max;
-        let bass_energy_multiplier = config.bass_energy_multiplier;
-        drop(config); // Release lock early
+        let bass_energy: f32 = config
+            .energy_bands
+            .iter()
+            .zip(band_energies)
+            .filter(|(band, _)| band.bass)
+            .map(|(_, energy)| energy.energy)
+            .sum();
+        bass_energy.min(1.0)
+    }
 
-        if self.buffer.len() < buffer_size {
+    pub fn calculate_spectral_centroid(&self) -> f32 {
+        let spectrum = self.stft.spectrum();
+        let bins = spectrum.bin_range(0.0, f32::MAX);
+        let magnitudes = &spectrum.magnitudes()[bins.clone()];
+        let total: f32 = magnitudes.iter().sum();
+        if total <= 0.0 {
             return 0.0;
         }
+        let weighted: f32 = bins.zip(magnitudes).map(|(bin, m)| bin as f32 * m).sum();
+        weighted / total * spectrum.bin_hz()
+    }
 
-        // Prepare data for FFT
-        let mut complex_buffer: Vec<Complex<f32>> = self.buffer[self.buffer.len() - buffer_size..]
+    pub fn calculate_spectral_spread(&self, centroid: f32) -> f32 {
+        let spectrum = self.stft.spectrum();
+        let bins = spectrum.bin_range(0.0, f32::MAX);
+        let magnitudes = &spectrum.magnitudes()[bins.clone()];
+        let total: f32 = magnitudes.iter().sum();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        let bin_hz = spectrum.bin_hz();
+        let variance: f32 = bins
+            .zip(magnitudes)
+            .map(|(bin, m)| (bin as f32 * bin_hz - centroid).powi(2) * m)
+            .sum();
+        (variance / total).sqrt()
+    }
+
+    pub fn calculate_spectral_rolloff(&self) -> f32 {
+        let spectrum = self.stft.spectrum();
+        let bins = spectrum.bin_range(0.0, f32::MAX);
+        let magnitudes = &spectrum.magnitudes()[bins.clone()];
+        let total: f32 = magnitudes.iter().map(|m| m * m).sum();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        let mut cumulative = 0.0;
+        for (bin, m) in bins.zip(magnitudes) {
+            cumulative += m * m;
+            if cumulative >= ROLLOFF_SHARE * total {
+                return bin as f32 * spectrum.bin_hz();
+            }
+        }
+        self.sample_rate / 2.0
+    }
+
+    pub fn calculate_spectral_flatness(&self) -> f32 {
+        let spectrum = self.stft.spectrum();
+        let powers: Vec<f32> = spectrum.magnitudes()[spectrum.bin_range(0.0, f32::MAX)]
             .iter()
-            .map(|&x| Complex::new(x, 0.0))
+            .map(|m| m * m)
             .collect();
+        let mean = powers.iter().sum::<f32>() / powers.len().max(1) as f32;
+        if mean <= 0.0 {
+            return 0.0;
+        }
+        // Empty bins would pull the geometric mean to zero
+        let log_mean = powers
+            .iter()
+            .map(|p| (p + f32::MIN_POSITIVE).ln())
+            .sum::<f32>()
+            / powers.len() as f32;
+        (log_mean.exp() / mean).min(1.0)
+    }
 
-        // Perform FFT
-        let fft = self.fft_planner.plan_fft_forward(buffer_size);
-        fft.process(&mut complex_buffer);
-
-        // Calculate which FFT bin corresponds to our bass cutoff frequency
-        let bin_freq = sample_rate / buffer_size as f32;
-        let bass_bin_max = (bass_freq_max / bin_freq) as usize;
+    pub fn calculate_zero_crossing_rate(&self, window: Range<usize>) -> f32 {
+        let samples = &self.buffer[window];
+        if samples.len() < 2 {
+            return 0.0;
+        }
+        let crossings = samples
+            .windows(2)
+            .filter(|pair| (pair[0] >= 0.0) != (pair[1] >= 0.0))
+            .count();
+        crossings as f32 / (samples.len() - 1) as f32
+    }
 
-        // Ensure we have at least one bin to analyze (need at least 2 for range [1..2])
-        if bass_bin_max < 2 {
+    pub fn calculate_crest_factor(&self, window: Range<usize>) -> f32 {
+        let samples = &self.buffer[window];
+        let sum_squares: f32 = samples.iter().map(|&x| x * x).sum();
+        if sum_squares <= 0.0 {
             return 0.0;
         }
+        let rms = (sum_squares / samples.len() as f32).sqrt();
+        let peak = samples.iter().fold(0.0f32, |peak, x| peak.max(x.abs()));
+        peak / rms
+    }
+
+    pub fn calculate_band_spectrum(&self) -> BandSpectrum {
+        let config = self.config.read().unwrap();
+        BandSpectrum::from_spectrum(
+            self.stft.spectrum(),
+            config.spectrum_scale,
+            config.spectrum_bands,
+            config.spectrum_min_freq,
+            config.spectrum_max_freq,
+            config.spectrum_db_floor,
+        )
+    }
+
+    /// Mel band levels and MFCCs of the current frame's spectrum.
+    pub fn calculate_mel_features(&mut self) -> (Vec<f32>, Vec<f32>) {
+        let (bands, coefficients) = {
+            let config = self.config.read().unwrap();
+            (config.mel_bands, config.mfcc_coefficients)
+        };
+        let mel_energies = self.mel_filterbank.energies(self.stft.spectrum(), bands);
+        let mfcc = mel::mfcc(&mel_energies, coefficients);
+        (mel_energies, mfcc)
+    }
+
+    /// Splits the current frame's spectrum into harmonic and percussive
+    /// parts, if enabled. Returns their shares of the frame's power.
+    pub fn separate_sources(&mut self) -> (f32, f32) {
+        let config = self.config.read().unwrap();
+        if !config.hpss_enabled {
+            self.hpss.reset();
+            return (0.0, 0.0);
+        }
+        let hop_seconds = hop_size(&config) as f32 / self.sample_rate;
+        let spectrum = self.stft.spectrum();
+        let params = HpssParams {
+            harmonic_frames: (config.hpss_harmonic_seconds / hop_seconds).round() as usize,
+            percussive_bins: (config.hpss_percussive_hz / spectrum.bin_hz()).round() as usize,
+        };
+        self.hpss.process(spectrum, params)
+    }
 
-        // Calculate bass energy (sum of magnitudes in bass range)
-        let bass_energy: f32 = complex_buffer[1..bass_bin_max.min(buffer_size / 2)]
+    /// Runs onset detection on the current frame's spectrum, or only its
+    /// percussive part if so configured.
+    pub fn detect_onsets(&mut self, time: f64) -> Vec<Onset> {
+        let config = self.config.read().unwrap();
+        let params = onset_params(&config, self.sample_rate);
+        let spectrum = if config.hpss_enabled && config.percussive_onsets {
+            self.hpss.percussive()
+        } else {
+            self.stft.spectrum()
+        };
+        let bands: Vec<_> = config
+            .energy_bands
             .iter()
-            .map(|c| c.norm())
-            .sum();
+            .map(|band| (band.name.as_str(), band.min_freq, band.max_freq))
+            .collect();
+
+        let mut onsets = Vec::new();
+        self.onset_detector
+            .process(spectrum, time, &bands, params, &mut onsets);
+        onsets
+    }
 
-        // Calculate total energy for normalization
-        let total_energy: f32 = complex_buffer[1..buffer_size / 2]
+    /// Labels the current frame's percussive onsets as kick, snare or hat,
+    /// with the same tuning and spectrum as [`Self::detect_onsets`].
+    pub fn classify_drums(&mut self, time: f64) -> Vec<DrumHit> {
+        let config = self.config.read().unwrap();
+        let params = onset_params(&config, self.sample_rate);
+        let spectrum = if config.hpss_enabled && config.percussive_onsets {
+            self.hpss.percussive()
+        } else {
+            self.stft.spectrum()
+        };
+        self.drum_classifier.process(spectrum, time, params)
+    }
+
+    /// Advances the beat tracker by one hop of onset envelope.
+    ///
+    /// The envelope is the mean flux of the bass bands, since the kick carries
+    /// the beat in dance music and hats would otherwise pull the phase to the
+    /// offbeat. Without bass bands the full-spectrum flux is used.
+    pub fn track_beats(&mut self, time: f64) -> (Tempo, Option<Beat>) {
+        let config = self.config.read().unwrap();
+        let hop_seconds = hop_size(&config) as f32 / self.sample_rate;
+        let bass_flux: Vec<f32> = config
+            .energy_bands
             .iter()
-            .map(|c| c.norm())
-            .sum();
+            .zip(self.onset_detector.band_novelty())
+            .filter(|(band, _)| band.bass)
+            .map(|(_, &flux)| flux)
+            .collect();
+        let envelope = if bass_flux.is_empty() {
+            self.onset_detector.novelty()
+        } else {
+            bass_flux.iter().sum::<f32>() / bass_flux.len() as f32
+        };
+
+        let beat = self.beat_tracker.process(
+            envelope,
+            time,
+            hop_seconds,
+            (config.tempo_min_bpm, config.tempo_max_bpm),
+        );
+        (self.beat_tracker.tempo(time), beat)
+    }
+
+    /// Runs the pitch tracker on a full analysis window.
+    pub fn track_pitch(&mut self, window: Range<usize>) -> Pitch {
+        let (buffer_size, params) = {
+            let config = self.config.read().unwrap();
+            let params = PitchParams {
+                min_freq: config.pitch_min_freq,
+                max_freq: config.pitch_max_freq,
+                threshold: config.pitch_threshold,
+            };
+            (config.buffer_size, params)
+        };
+        if window.len() < buffer_size {
+            return Pitch::default();
+        }
+        self.pitch_tracker
+            .detect(&self.buffer[window], self.sample_rate, params)
+    }
+
+    /// Folds the current frame's spectrum into pitch classes and updates the
+    /// rolling key estimate. Returns the chroma scaled so its peak is 1.
+    pub fn estimate_key(&mut self) -> ([f32; 12], Option<KeyEstimate>) {
+        let config = self.config.read().unwrap();
+        let hop_seconds = hop_size(&config) as f32 / self.sample_rate;
+        let chroma = chroma::chromagram(self.stft.spectrum());
+        let key = self
+            .key_estimator
+            .update(&chroma, hop_seconds, config.key_window_seconds);
+
+        let peak = chroma.iter().copied().fold(0.0, f32::max);
+        let normalized = if peak > 0.0 {
+            chroma.map(|power| power / peak)
+        } else {
+            chroma
+        };
+        (normalized, key)
+    }
+
+    /// Ranks this frame's levels against the adaptive window.
+    pub fn rank_levels(
+        &mut self,
+        time: f64,
+        loudness: f32,
+        bass_energy: f32,
+        band_energies: &[BandEnergy],
+    ) -> RelativeLevels {
+        let window_seconds = self.config.read().unwrap().adaptive_window_seconds as f64;
+        let energies: Vec<f32> = band_energies.iter().map(|band| band.energy).collect();
+        self.level_stats
+            .update(time, loudness, bass_energy, &energies, window_seconds)
+    }
+
+    /// Runs every level metric through its configured envelope follower.
+    pub fn follow_envelopes(&mut self, time: f64, levels: Levels) -> LevelEnvelopes {
+        let config = self.config.read().unwrap();
+        self.envelope_followers.update(time, &config, levels)
+    }
+
+    /// Returns (balance, phase correlation, width) for the left/right buffers.
+    pub fn calculate_stereo_field(&self, window: Range<usize>) -> (f32, f32, f32) {
+        if window.is_empty() {
+            return (0.0, 0.0, 0.0);
+        }
+
+        let mut sum_ll = 0.0;
+        let mut sum_rr = 0.0;
+        let mut sum_lr = 0.0;
+        let left = &self.left_buffer[window.clone()];
+        let right = &self.right_buffer[window];
+        for (&l, &r) in left.iter().zip(right) {
+            sum_ll += l * l;
+            sum_rr += r * r;
+            sum_lr += l * r;
+        }
 
-        // Return normalized bass energy (0-1 range)
-        if total_energy > 0.0 {
-            let normalized = bass_energy / total_energy;
-            // Scale it up to make drops more obvious
-            (normalized * bass_energy_multiplier).min(1.0)
+        // Balance from channel RMS levels
+        let (rms_l, rms_r) = (sum_ll.sqrt(), sum_rr.sqrt());
+        let balance = if rms_l + rms_r > 0.0 {
+            (rms_r - rms_l) / (rms_l + rms_r)
         } else {
             0.0
+        };
+
+        // Normalized cross-correlation at zero lag
+        let denominator = (sum_ll * sum_rr).sqrt();
+        let correlation = if denominator > 0.0 {
+            (sum_lr / denominator).clamp(-1.0, 1.0)
+        } else {
+            0.0
+        };
+
+        // Mid/side energies: mid = (l + r) / 2, side = (l - r) / 2
+        let mid_energy = (sum_ll + sum_rr + 2.0 * sum_lr) / 4.0;
+        let side_energy = (sum_ll + sum_rr - 2.0 * sum_lr) / 4.0;
+        let width = if mid_energy + side_energy > 0.0 {
+            (side_energy / (mid_energy + side_energy)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        (balance, correlation, width)
+    }
+
+    /// Analyzes the next hop of audio, if a full hop has arrived since the
+    /// last call. Call repeatedly until it returns `None` to catch up.
+    pub fn next_hop(&mut self) -> Option<AudioMetrics> {
+        self.pull_samples();
+
+        let (buffer_size, hop_size) = {
+            let config = self.config.read().unwrap();
+            (config.buffer_size, hop_size(&config))
+        };
+        if self.unanalyzed < hop_size {
+            self.consumer
+                .wake_after((hop_size - self.unanalyzed) * self.channels);
+            return None;
         }
+        self.unanalyzed -= hop_size;
+        self.frames_analyzed += hop_size as u64;
+
+        let end = self.buffer.len() - self.unanalyzed;
+        let window = end.saturating_sub(buffer_size)..end;
+        let hop = end.saturating_sub(hop_size)..end;
+
+        Some(self.analyze(window, hop))
     }
 
-    pub fn analyze(&mut self) -> AudioMetrics {
-        let loudness = self.calculate_loudness();
-        let bass_energy = self.calculate_bass_energy();
+    /// Drops the per-band state when bands are added, removed, renamed or
+    /// reordered, so no band inherits another's smoothing or history.
+    fn sync_bands(&mut self) {
+        let config = self.config.read().unwrap();
+        let names = config.energy_bands.iter().map(|band| &band.name);
+        if names.clone().eq(&self.band_names) {
+            return;
+        }
+        self.band_names = names.cloned().collect();
+        self.band_state.clear();
+        self.onset_detector.reset_bands();
+        self.level_stats.reset_bands();
+        self.envelope_followers.reset_bands();
+    }
+
+    /// Analyzes `window`, of which `hop` is the audio new since the last step.
+    fn analyze(&mut self, window: Range<usize>, hop: Range<usize>) -> AudioMetrics {
+        self.sync_bands();
+        let (buffer_size, window_function) = {
+            let config = self.config.read().unwrap();
+            (config.buffer_size, config.window_function)
+        };
+
+        // One spectrum per frame, shared by every spectral feature below
+        if window.len() == buffer_size {
+            self.stft.process(
+                &self.buffer[window.clone()],
+                window_function,
+                self.sample_rate,
+            );
+        } else {
+            self.stft.clear();
+        }
+
+        let time = self.frames_analyzed as f64 / self.sample_rate as f64;
+        let loudness = self.calculate_loudness(window.clone());
+        let meter = self.measure_loudness(hop);
+        let band_energies = self.calculate_band_energies();
+        let bass_energy = self.bass_energy(&band_energies);
+        let zero_crossing_rate = self.calculate_zero_crossing_rate(window.clone());
+        let crest_factor = self.calculate_crest_factor(window.clone());
+        let pitch = self.track_pitch(window.clone());
+        let (stereo_balance, phase_correlation, stereo_width) = self.calculate_stereo_field(window);
+        let spectrum = self.calculate_band_spectrum();
+        let spectral_centroid = self.calculate_spectral_centroid();
+        let spectral_spread = self.calculate_spectral_spread(spectral_centroid);
+        let spectral_rolloff = self.calculate_spectral_rolloff();
+        let spectral_flatness = self.calculate_spectral_flatness();
+        let (mel_energies, mfcc) = self.calculate_mel_features();
+        let (harmonic_energy, percussive_energy) = self.separate_sources();
+        let onsets = self.detect_onsets(time);
+        let drum_hits = self.classify_drums(time);
+        let (tempo, beat) = self.track_beats(time);
+        let (chroma, key) = self.estimate_key();
+        let relative = self.rank_levels(time, loudness, bass_energy, &band_energies);
+        let energies: Vec<f32> = band_energies.iter().map(|band| band.energy).collect();
+        let envelopes = self.follow_envelopes(
+            time,
+            Levels {
+                loudness,
+                bass_energy,
+                stereo_width,
+                stereo_balance,
+                phase_correlation,
+                spectral_centroid,
+                band_energies: &energies,
+            },
+        );
 
         AudioMetrics {
+            time,
             loudness,
+            meter,
             bass_energy,
+            band_energies,
+            stereo_balance,
+            phase_correlation,
+            stereo_width,
+            spectrum,
+            spectral_centroid,
+            spectral_spread,
+            spectral_rolloff,
+            spectral_flatness,
+            zero_crossing_rate,
+            crest_factor,
+            mel_energies,
+            mfcc,
+            harmonic_energy,
+            percussive_energy,
+            onset_strength: self.onset_detector.novelty(),
+            onsets,
+            drum_hits,
+            tempo,
+            beat,
+            pitch,
+            chroma,
+            key,
+            relative,
+            envelopes,
         }
     }
 
     pub fn get_buffer(&self) -> Vec<f32> {
-        self.buffer.clone()
+        self.buffer[self.display_range()].to_vec()
+    }
+
+    pub fn get_stereo_buffers(&self) -> (Vec<f32>, Vec<f32>) {
+        let range = self.display_range();
+        (
+            self.left_buffer[range.clone()].to_vec(),
+            self.right_buffer[range].to_vec(),
+        )
+    }
+
+    /// The most recent window's worth of samples, for display.
+    fn display_range(&self) -> Range<usize> {
+        let buffer_size = self.config.read().unwrap().buffer_size;
+        self.buffer.len().saturating_sub(buffer_size)..self.buffer.len()
+    }
+}
+
+/// Samples per analysis step. Hops longer than the window would skip audio.
+fn hop_size(config: &AudioConfig) -> usize {
+    config.hop_size.clamp(1, config.buffer_size.max(1))
+}
+
+/// Onset detection tuning from `config`, with the median window in hops.
+fn onset_params(config: &AudioConfig, sample_rate: f32) -> OnsetParams {
+    let hop_seconds = hop_size(config) as f32 / sample_rate;
+    OnsetParams {
+        threshold: config.onset_threshold,
+        delta: config.onset_delta,
+        median_frames: (config.onset_median_seconds / hop_seconds).round() as usize,
+        min_interval: config.onset_min_interval_ms as f64 / 1000.0,
+    }
+}
+
+fn downmix(frame: &[f32], channel_mode: ChannelMode) -> f32 {
+    match channel_mode {
+        ChannelMode::MonoSum => frame.iter().sum::<f32>() / frame.len() as f32,
+        ChannelMode::Left => frame[0],
+        ChannelMode::Right => *frame.get(1).unwrap_or(&frame[0]),
+        ChannelMode::Max => frame
+            .iter()
+            .copied()
+            .fold(0.0, |max, s| if s.abs() > max.abs() { s } else { max }),
     }
 }
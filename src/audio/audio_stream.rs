@@ -1,10 +1,13 @@
 use super::analyzer::AudioAnalyzer;
+use super::ring_buffer::SampleProducer;
+use super::source::AudioSource;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{FromSample, Sample, SampleFormat};
 use log::debug;
 use std::sync::{Arc, Mutex};
 
 pub struct AudioStream {
+    name: String,
     _stream: cpal::Stream,
 }
 
@@ -13,19 +16,28 @@ impl AudioStream {
         device: &cpal::Device,
         config: &cpal::StreamConfig,
         sample_format: SampleFormat,
-        analyzer: Arc<Mutex<AudioAnalyzer>>,
+        producer: SampleProducer,
     ) -> Result<Self, anyhow::Error> {
         let stream = match sample_format {
-            cpal::SampleFormat::F32 => build_stream::<f32>(device, config, analyzer)?,
-            cpal::SampleFormat::I16 => build_stream::<i16>(device, config, analyzer)?,
-            cpal::SampleFormat::U16 => build_stream::<u16>(device, config, analyzer)?,
+            cpal::SampleFormat::F32 => build_stream::<f32>(device, config, producer)?,
+            cpal::SampleFormat::I16 => build_stream::<i16>(device, config, producer)?,
+            cpal::SampleFormat::U16 => build_stream::<u16>(device, config, producer)?,
             _ => return Err(anyhow::anyhow!("Unsupported sample format")),
         };
 
         stream.play()?;
         debug!("Audio stream started successfully");
 
-        Ok(Self { _stream: stream })
+        Ok(Self {
+            name: device.name().unwrap_or_default(),
+            _stream: stream,
+        })
+    }
+}
+
+impl AudioSource for AudioStream {
+    fn label(&self) -> String {
+        format!("Listening to {}", self.name)
     }
 }
 
@@ -56,13 +68,19 @@ pub fn create_audio_stream(
         stream_config.sample_rate.0, stream_config.channels, sample_format
     );
 
-    AudioStream::new(&device, &stream_config, sample_format, analyzer).ok()
+    let producer = {
+        let mut analyzer = analyzer.lock().unwrap();
+        analyzer.set_stream_config(&stream_config);
+        analyzer.sample_producer()
+    };
+
+    AudioStream::new(&device, &stream_config, sample_format, producer).ok()
 }
 
 fn build_stream<T>(
     device: &cpal::Device,
     config: &cpal::StreamConfig,
-    analyzer: Arc<Mutex<AudioAnalyzer>>,
+    producer: SampleProducer,
 ) -> Result<cpal::Stream, anyhow::Error>
 where
     T: Sample + FromSample<f32> + cpal::SizedSample,
@@ -70,9 +88,9 @@ where
 {
     let stream = device.build_input_stream(
         config,
+        // Real-time thread: no locking or allocation here, just convert and enqueue
         move |data: &[T], _: &cpal::InputCallbackInfo| {
-            let samples: Vec<f32> = data.iter().map(|&s| s.to_sample()).collect();
-            analyzer.lock().unwrap().add_samples(&samples);
+            producer.push_iter(data.iter().map(|&s| s.to_sample()));
         },
         |err| eprintln!("Stream error: {err}"),
         None,
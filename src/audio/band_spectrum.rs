@@ -0,0 +1,145 @@
+use super::stft::Spectrum;
+use crate::config::FrequencyScale;
+
+/// Magnitude spectrum grouped into perceptually spaced bands.
+#[derive(Clone, Default, Debug)]
+pub struct BandSpectrum {
+    /// Center frequency of each band in Hz.
+    pub centers_hz: Vec<f32>,
+    /// Band level in dBFS (a full-scale sine reads ~0 dB).
+    pub db: Vec<f32>,
+    /// Band level mapped from `[db_floor, 0]` dB to 0-1.
+    pub normalized: Vec<f32>,
+}
+
+impl BandSpectrum {
+    /// Groups `spectrum` into `bands` bands between `min_hz` and `max_hz`.
+    pub fn from_spectrum(
+        spectrum: &Spectrum,
+        scale: FrequencyScale,
+        bands: usize,
+        min_hz: f32,
+        max_hz: f32,
+        db_floor: f32,
+    ) -> Self {
+        if spectrum.is_empty() || bands == 0 {
+            return Self::default();
+        }
+
+        let nyquist = spectrum.bin_hz() * (spectrum.magnitudes().len() - 1) as f32;
+        let max_hz = max_hz.min(nyquist);
+        let min_hz = min_hz.clamp(1.0, max_hz * 0.5);
+        let edges = scale.band_edges(bands, min_hz, max_hz);
+
+        let mut result = Self {
+            centers_hz: Vec::with_capacity(bands),
+            db: Vec::with_capacity(bands),
+            normalized: Vec::with_capacity(bands),
+        };
+
+        for edge in edges.windows(2) {
+            let (low, high) = (edge[0], edge[1]);
+            let center = scale.to_hz((scale.to_scale(low) + scale.to_scale(high)) / 2.0);
+
+            // Power-sum the bins in the band. Bands narrower than one bin
+            // (common at low frequencies) take the bin containing their center.
+            let bins = spectrum.bin_range(low, high);
+            let magnitudes = spectrum.magnitudes();
+            let amplitude = if !bins.is_empty() {
+                let power: f32 = magnitudes[bins].iter().map(|m| m * m).sum();
+                (power / spectrum.noise_bandwidth()).sqrt()
+            } else {
+                magnitudes[spectrum.bin_for_frequency(center)]
+            };
+
+            let db = (20.0 * amplitude.max(1e-10).log10()).max(db_floor);
+            result.centers_hz.push(center);
+            result.db.push(db);
+            result
+                .normalized
+                .push((1.0 - db / db_floor).clamp(0.0, 1.0));
+        }
+
+        result
+    }
+}
+
+impl FrequencyScale {
+    /// Converts Hz to this scale's units.
+    pub fn to_scale(self, hz: f32) -> f32 {
+        match self {
+            FrequencyScale::Log => hz.ln(),
+            // Traunmüller's Bark approximation
+            FrequencyScale::Bark => 26.81 * hz / (1960.0 + hz) - 0.53,
+            // Glasberg & Moore ERB-rate
+            FrequencyScale::Erb => 21.4 * (1.0 + 0.00437 * hz).log10(),
+        }
+    }
+
+    /// Converts this scale's units back to Hz.
+    pub fn to_hz(self, value: f32) -> f32 {
+        match self {
+            FrequencyScale::Log => value.exp(),
+            FrequencyScale::Bark => 1960.0 * (value + 0.53) / (26.28 - value),
+            FrequencyScale::Erb => (10f32.powf(value / 21.4) - 1.0) / 0.00437,
+        }
+    }
+
+    /// `bands + 1` edges evenly spaced on this scale.
+    pub fn band_edges(self, bands: usize, min_hz: f32, max_hz: f32) -> Vec<f32> {
+        let (low, high) = (self.to_scale(min_hz), self.to_scale(max_hz));
+        (0..=bands)
+            .map(|i| self.to_hz(low + (high - low) * i as f32 / bands as f32))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::stft::Stft;
+    use crate::config::WindowFunction;
+    use std::f32::consts::TAU;
+
+    fn sine_spectrum(frequency: f32) -> BandSpectrum {
+        let samples: Vec<f32> = (0..4096)
+            .map(|n| (TAU * frequency * n as f32 / 48000.0).sin())
+            .collect();
+        let mut stft = Stft::new();
+        stft.process(&samples, WindowFunction::Hann, 48000.0);
+        BandSpectrum::from_spectrum(
+            stft.spectrum(),
+            FrequencyScale::Log,
+            32,
+            20.0,
+            20000.0,
+            -80.0,
+        )
+    }
+
+    #[test]
+    fn sine_peaks_in_the_band_containing_its_frequency() {
+        let spectrum = sine_spectrum(1000.0);
+        let loudest = (0..spectrum.db.len())
+            .max_by(|&a, &b| spectrum.db[a].total_cmp(&spectrum.db[b]))
+            .unwrap();
+
+        let edges = FrequencyScale::Log.band_edges(32, 20.0, 20000.0);
+        assert!(edges[loudest] <= 1000.0 && 1000.0 < edges[loudest + 1]);
+        assert!(
+            spectrum.db[loudest].abs() < 1.0,
+            "{} dB",
+            spectrum.db[loudest]
+        );
+    }
+
+    #[test]
+    fn scales_round_trip() {
+        for scale in FrequencyScale::ALL {
+            for hz in [20.0, 440.0, 12000.0] {
+                let back = scale.to_hz(scale.to_scale(hz));
+                assert!((back - hz).abs() / hz < 1e-3, "{scale:?}: {hz} -> {back}");
+            }
+        }
+    }
+}
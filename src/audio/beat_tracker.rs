@@ -0,0 +1,329 @@
+use std::collections::VecDeque;
+
+/// Seconds of onset envelope the tempo is estimated from.
+const HISTORY_SECONDS: f32 = 6.0;
+
+/// Envelope needed before the first estimate.
+const MIN_HISTORY_SECONDS: f32 = 2.0;
+
+/// Multiples of the beat period summed when scoring a tempo. Using several
+/// periods sharpens the estimate well below the one-hop lag resolution.
+const COMB_PERIODS: usize = 6;
+
+/// Resolution of the tempo search.
+const BPM_STEP: f32 = 0.25;
+
+/// Tempo the search prefers when octave candidates score alike.
+const PREFERRED_BPM: f32 = 120.0;
+
+/// Fraction of the tempo error corrected per hop, for following gradual changes.
+const TEMPO_ADAPT: f32 = 0.05;
+
+/// Estimates further off than this ratio are treated as a different tempo
+/// rather than drift, and only adopted once they persist.
+const TEMPO_JUMP_RATIO: f64 = 0.08;
+
+/// How long a different tempo must persist before it is adopted, in seconds.
+const TEMPO_JUMP_SECONDS: f64 = 1.5;
+
+/// Fraction of the beat phase error corrected per hop.
+const PHASE_ADAPT: f64 = 0.1;
+
+/// Phase errors beyond this fraction of a beat are treated as an offbeat
+/// pattern (hats in a breakdown) rather than drift.
+const PHASE_JUMP_FRACTION: f64 = 0.25;
+
+/// How long an offbeat phase must persist before it is adopted, in seconds.
+const PHASE_JUMP_SECONDS: f64 = 4.0;
+
+/// Below this confidence an estimate doesn't move the tempo or beat phase.
+const MIN_CONFIDENCE: f32 = 0.1;
+
+/// How long beats keep coming at the last confident tempo, in seconds. Carries
+/// the beat through breakdowns and the upheaval of a drop hitting.
+const COAST_SECONDS: f64 = 8.0;
+
+/// Current tempo estimate.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct Tempo {
+    /// Beats per minute, 0 until the tracker has locked on.
+    pub bpm: f32,
+    /// How periodic the onset envelope is at this tempo, 0-1.
+    pub confidence: f32,
+    /// Position inside the current beat: 0 on the beat, rising towards 1.
+    pub phase: f32,
+}
+
+/// A predicted beat.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Beat {
+    /// Stream time in seconds of the beat.
+    pub time: f64,
+    pub bpm: f32,
+}
+
+/// Autocorrelation tempo estimation with a phase-locked beat clock.
+pub struct BeatTracker {
+    envelope: VecDeque<f32>,
+    centered: Vec<f32>,
+    autocorrelation: Vec<f32>,
+    /// Beat period in seconds, 0 until locked.
+    period: f64,
+    confidence: f32,
+    /// A differing tempo estimate and when it first appeared.
+    jump_candidate: Option<(f64, f64)>,
+    next_beat: Option<f64>,
+    /// When the measured phase started disagreeing by more than a quarter beat.
+    phase_jump_since: Option<f64>,
+    last_confident: Option<f64>,
+}
+
+impl BeatTracker {
+    pub fn new() -> Self {
+        Self {
+            envelope: VecDeque::new(),
+            centered: Vec::new(),
+            autocorrelation: Vec::new(),
+            period: 0.0,
+            confidence: 0.0,
+            jump_candidate: None,
+            next_beat: None,
+            phase_jump_since: None,
+            last_confident: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Feeds one hop of onset envelope, returning the beat if one fell in
+    /// this hop. `hop_seconds` is the time between envelope values.
+    pub fn process(
+        &mut self,
+        onset_strength: f32,
+        time: f64,
+        hop_seconds: f32,
+        bpm_range: (f32, f32),
+    ) -> Option<Beat> {
+        let capacity = (HISTORY_SECONDS / hop_seconds).ceil() as usize;
+        self.envelope.push_back(onset_strength);
+        while self.envelope.len() > capacity {
+            self.envelope.pop_front();
+        }
+        if (self.envelope.len() as f32) < MIN_HISTORY_SECONDS / hop_seconds {
+            return None;
+        }
+
+        self.update_autocorrelation();
+        let estimate = self.estimate_bpm(bpm_range, hop_seconds);
+        self.confidence = estimate.map_or(0.0, |(_, confidence)| confidence);
+        let confident = self.confidence >= MIN_CONFIDENCE;
+        if let Some((bpm, _)) = estimate.filter(|_| confident) {
+            self.update_tempo(60.0 / bpm as f64, time);
+            self.last_confident = Some(time);
+        }
+        if self.period <= 0.0 {
+            return None;
+        }
+
+        // Nudge the beat clock towards where the envelope says beats are
+        let next_beat = match self.next_beat {
+            Some(next) if confident => {
+                let measured = time - self.last_beat_offset(hop_seconds);
+                let error = wrap(measured - (next - self.period), self.period);
+                if error.abs() <= self.period * PHASE_JUMP_FRACTION {
+                    self.phase_jump_since = None;
+                    next + error * PHASE_ADAPT
+                } else if time - *self.phase_jump_since.get_or_insert(time) >= PHASE_JUMP_SECONDS {
+                    self.phase_jump_since = None;
+                    next + error
+                } else {
+                    next
+                }
+            }
+            Some(next) => next,
+            // Just locked, which needs a confident estimate
+            None => time - self.last_beat_offset(hop_seconds) + self.period,
+        };
+
+        let tracking = self
+            .last_confident
+            .is_some_and(|last| time - last <= COAST_SECONDS);
+        if time >= next_beat {
+            self.next_beat = Some(next_beat + self.period);
+            tracking.then(|| Beat {
+                time: next_beat,
+                bpm: self.bpm(),
+            })
+        } else {
+            self.next_beat = Some(next_beat);
+            None
+        }
+    }
+
+    pub fn tempo(&self, time: f64) -> Tempo {
+        let phase = match self.next_beat {
+            Some(next) if self.period > 0.0 => {
+                (1.0 - (next - time) / self.period).clamp(0.0, 1.0) as f32
+            }
+            _ => 0.0,
+        };
+        Tempo {
+            bpm: self.bpm(),
+            confidence: self.confidence,
+            phase,
+        }
+    }
+
+    fn bpm(&self) -> f32 {
+        if self.period > 0.0 {
+            (60.0 / self.period) as f32
+        } else {
+            0.0
+        }
+    }
+
+    /// Autocorrelation of the mean-removed envelope for lags up to half the
+    /// history, beyond which too few products are left to average.
+    fn update_autocorrelation(&mut self) {
+        let mean = self.envelope.iter().sum::<f32>() / self.envelope.len() as f32;
+        self.centered.clear();
+        self.centered.extend(self.envelope.iter().map(|e| e - mean));
+
+        let len = self.centered.len();
+        self.autocorrelation.clear();
+        self.autocorrelation.extend((0..=len / 2).map(|lag| {
+            self.centered[lag..]
+                .iter()
+                .zip(&self.centered)
+                .map(|(a, b)| a * b)
+                .sum::<f32>()
+                / (len - lag) as f32
+        }));
+    }
+
+    /// Best tempo in `bpm_range` and its confidence.
+    fn estimate_bpm(&self, bpm_range: (f32, f32), hop_seconds: f32) -> Option<(f32, f32)> {
+        let energy = *self.autocorrelation.first()?;
+        if energy <= f32::EPSILON {
+            return None;
+        }
+
+        let (min_bpm, max_bpm) = (bpm_range.0.max(1.0), bpm_range.1.max(bpm_range.0 + 1.0));
+        let steps = ((max_bpm - min_bpm) / BPM_STEP) as usize;
+        let score = |step: usize| {
+            let bpm = min_bpm + step as f32 * BPM_STEP;
+            let comb = self.comb(60.0 / bpm / hop_seconds)?;
+            let octaves = (bpm / PREFERRED_BPM).log2();
+            Some((comb * (-0.5 * octaves * octaves).exp(), comb))
+        };
+        let scores: Vec<Option<(f32, f32)>> = (0..=steps).map(score).collect();
+        let best = (0..=steps)
+            .filter(|&step| scores[step].is_some())
+            .max_by(|&a, &b| scores[a].unwrap().0.total_cmp(&scores[b].unwrap().0))?;
+        let (best_score, comb) = scores[best]?;
+
+        // Parabolic refinement between neighbouring grid steps
+        let neighbour = |step: Option<usize>| {
+            step.and_then(|s| scores.get(s).copied().flatten())
+                .map_or(best_score, |(score, _)| score)
+        };
+        let (before, after) = (neighbour(best.checked_sub(1)), neighbour(Some(best + 1)));
+        let curvature = before - 2.0 * best_score + after;
+        let shift = if curvature < 0.0 {
+            (0.5 * (before - after) / curvature).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        };
+
+        let bpm = min_bpm + (best as f32 + shift) * BPM_STEP;
+        Some((bpm, (comb / energy).clamp(0.0, 1.0)))
+    }
+
+    /// Mean autocorrelation at the first [`COMB_PERIODS`] multiples of `lag`.
+    fn comb(&self, lag: f32) -> Option<f32> {
+        let values: Vec<f32> = (1..=COMB_PERIODS)
+            .map_while(|k| interpolate(&self.autocorrelation, lag * k as f32))
+            .collect();
+        (!values.is_empty()).then(|| values.iter().sum::<f32>() / values.len() as f32)
+    }
+
+    /// Moves the tempo towards a new period estimate: gradually when close,
+    /// at once when a different tempo has persisted.
+    fn update_tempo(&mut self, measured: f64, time: f64) {
+        if self.period <= 0.0 {
+            self.period = measured;
+            return;
+        }
+
+        if (measured / self.period - 1.0).abs() <= TEMPO_JUMP_RATIO {
+            self.period += (measured - self.period) * TEMPO_ADAPT as f64;
+            self.jump_candidate = None;
+            return;
+        }
+
+        match self.jump_candidate {
+            Some((candidate, since)) if (measured / candidate - 1.0).abs() <= TEMPO_JUMP_RATIO => {
+                if time - since >= TEMPO_JUMP_SECONDS {
+                    self.period = measured;
+                    self.jump_candidate = None;
+                }
+            }
+            _ => self.jump_candidate = Some((measured, time)),
+        }
+    }
+
+    /// Seconds since the most recent beat, from folding the envelope at the
+    /// current period.
+    fn last_beat_offset(&self, hop_seconds: f32) -> f64 {
+        let period = self.period as f32 / hop_seconds;
+        let newest = self.centered.len() - 1;
+        let candidates = period.ceil() as usize;
+
+        let scores: Vec<f32> = (0..candidates)
+            .map(|offset| {
+                (0..)
+                    .map(|k| offset as f32 + k as f32 * period)
+                    .take_while(|&back| back <= newest as f32)
+                    .filter_map(|back| interpolate(&self.centered, newest as f32 - back))
+                    .sum()
+            })
+            .collect();
+
+        let best = (0..candidates)
+            .max_by(|&a, &b| scores[a].total_cmp(&scores[b]))
+            .unwrap_or(0);
+
+        // Parabolic refinement between neighbouring offsets (circular)
+        let before = scores[(best + candidates - 1) % candidates];
+        let after = scores[(best + 1) % candidates];
+        let curvature = before - 2.0 * scores[best] + after;
+        let shift = if curvature < 0.0 {
+            (0.5 * (before - after) / curvature).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        };
+
+        ((best as f32 + shift) * hop_seconds) as f64
+    }
+}
+
+/// Linear interpolation of `values` at a fractional index.
+fn interpolate(values: &[f32], index: f32) -> Option<f32> {
+    if index < 0.0 {
+        return None;
+    }
+    let low = index.floor() as usize;
+    let frac = index - low as f32;
+    match (values.get(low), values.get(low + 1)) {
+        (Some(a), Some(b)) => Some(a + (b - a) * frac),
+        (Some(a), None) if frac == 0.0 => Some(*a),
+        _ => None,
+    }
+}
+
+/// Wraps `value` into `[-period / 2, period / 2)`.
+fn wrap(value: f64, period: f64) -> f64 {
+    (value + period / 2.0).rem_euclid(period) - period / 2.0
+}
@@ -0,0 +1,168 @@
+use std::f64::consts::PI;
+
+use crate::config::{FilterKind, FilterStage};
+
+/// Direct form I biquad, normalized so a0 = 1.
+#[derive(Clone, Debug)]
+pub struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Default for Biquad {
+    /// A filter that passes the signal unchanged.
+    fn default() -> Self {
+        Self::new([1.0, 0.0, 0.0], [0.0, 0.0])
+    }
+}
+
+impl Biquad {
+    /// Feedforward coefficients `b0, b1, b2` and feedback `a1, a2`.
+    pub fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self {
+            b,
+            a,
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    /// Filter for `stage` at `sample_rate`, after the RBJ audio EQ cookbook.
+    /// Bypassed stages pass the signal unchanged.
+    pub fn from_stage(stage: &FilterStage, sample_rate: f32) -> Self {
+        if !stage.enabled {
+            return Self::default();
+        }
+        let nyquist = sample_rate as f64 / 2.0;
+        let frequency = (stage.frequency as f64).clamp(1.0, nyquist * 0.99);
+        let w0 = 2.0 * PI * frequency / sample_rate as f64;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * (stage.q as f64).max(0.01));
+        let gain = 10f64.powf(stage.gain_db as f64 / 40.0);
+        let shelf = 2.0 * gain.sqrt() * alpha;
+
+        let (b, a0, a) = match stage.kind {
+            FilterKind::HighPass => (
+                [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+                1.0 + alpha,
+                [-2.0 * cos, 1.0 - alpha],
+            ),
+            FilterKind::LowPass => (
+                [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+                1.0 + alpha,
+                [-2.0 * cos, 1.0 - alpha],
+            ),
+            FilterKind::BandPass => ([alpha, 0.0, -alpha], 1.0 + alpha, [-2.0 * cos, 1.0 - alpha]),
+            FilterKind::Notch => (
+                [1.0, -2.0 * cos, 1.0],
+                1.0 + alpha,
+                [-2.0 * cos, 1.0 - alpha],
+            ),
+            FilterKind::LowShelf => (
+                [
+                    gain * ((gain + 1.0) - (gain - 1.0) * cos + shelf),
+                    2.0 * gain * ((gain - 1.0) - (gain + 1.0) * cos),
+                    gain * ((gain + 1.0) - (gain - 1.0) * cos - shelf),
+                ],
+                (gain + 1.0) + (gain - 1.0) * cos + shelf,
+                [
+                    -2.0 * ((gain - 1.0) + (gain + 1.0) * cos),
+                    (gain + 1.0) + (gain - 1.0) * cos - shelf,
+                ],
+            ),
+            FilterKind::HighShelf => (
+                [
+                    gain * ((gain + 1.0) + (gain - 1.0) * cos + shelf),
+                    -2.0 * gain * ((gain - 1.0) + (gain + 1.0) * cos),
+                    gain * ((gain + 1.0) + (gain - 1.0) * cos - shelf),
+                ],
+                (gain + 1.0) - (gain - 1.0) * cos + shelf,
+                [
+                    2.0 * ((gain - 1.0) - (gain + 1.0) * cos),
+                    (gain + 1.0) - (gain - 1.0) * cos - shelf,
+                ],
+            ),
+        };
+        Self::new(b.map(|b| b / a0), a.map(|a| a / a0))
+    }
+
+    /// Takes over `other`'s coefficients but keeps this filter's state, so
+    /// retuning a running filter doesn't restart it.
+    pub fn retune(&mut self, other: &Biquad) {
+        self.b = other.b;
+        self.a = other.a;
+    }
+
+    pub fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44100.0;
+
+    /// Steady-state gain of `stage` for a sine at `frequency`, in dB.
+    fn gain_db(stage: &FilterStage, frequency: f32) -> f64 {
+        let mut filter = Biquad::from_stage(stage, SAMPLE_RATE);
+        let (mut input, mut output) = (0.0, 0.0);
+        for n in 0..SAMPLE_RATE as usize {
+            let x = (2.0 * PI * frequency as f64 * n as f64 / SAMPLE_RATE as f64).sin();
+            let y = filter.process(x);
+            // Skip the first half while the filter settles
+            if n >= SAMPLE_RATE as usize / 2 {
+                input += x * x;
+                output += y * y;
+            }
+        }
+        10.0 * (output / input).log10()
+    }
+
+    #[test]
+    fn high_pass_cuts_below_its_cutoff() {
+        // 12 dB per octave below the cutoff, flat well above it
+        let stage = FilterStage::new(FilterKind::HighPass, 100.0, 0.707, 0.0);
+        assert!(gain_db(&stage, 25.0) < -20.0);
+        assert!(gain_db(&stage, 2000.0).abs() < 0.1);
+        assert!((gain_db(&stage, 100.0) + 3.01).abs() < 0.1);
+    }
+
+    #[test]
+    fn notch_removes_only_its_frequency() {
+        let stage = FilterStage::new(FilterKind::Notch, 50.0, 10.0, 0.0);
+        assert!(gain_db(&stage, 50.0) < -40.0);
+        assert!(gain_db(&stage, 440.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn shelves_reach_their_gain() {
+        let low = FilterStage::new(FilterKind::LowShelf, 300.0, 0.707, -12.0);
+        assert!((gain_db(&low, 40.0) + 12.0).abs() < 0.5);
+        assert!(gain_db(&low, 5000.0).abs() < 0.5);
+
+        let high = FilterStage::new(FilterKind::HighShelf, 3000.0, 0.707, 6.0);
+        assert!((gain_db(&high, 15000.0) - 6.0).abs() < 0.5);
+        assert!(gain_db(&high, 100.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn bypassed_stages_pass_the_signal_unchanged() {
+        let stage = FilterStage {
+            enabled: false,
+            ..FilterStage::new(FilterKind::LowPass, 100.0, 0.707, 0.0)
+        };
+        let mut filter = Biquad::from_stage(&stage, SAMPLE_RATE);
+        for x in [1.0, -0.5, 0.25, 0.0] {
+            assert_eq!(filter.process(x), x);
+        }
+    }
+}
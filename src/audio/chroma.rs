@@ -0,0 +1,210 @@
+use super::stft::Spectrum;
+
+/// Frequency range folded into the chromagram. Below it neighbouring notes
+/// share an FFT bin, above it harmonics outweigh the notes played.
+const CHROMA_MIN_HZ: f32 = 100.0;
+const CHROMA_MAX_HZ: f32 = 5000.0;
+
+/// Krumhansl-Kessler probe-tone ratings of each scale degree, tonic first.
+const MAJOR_PROFILE: [f32; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+const MINOR_PROFILE: [f32; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+pub const PITCH_CLASS_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeyMode {
+    Major,
+    Minor,
+}
+
+/// A musical key: tonic pitch class (0 = C, 11 = B) and mode.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Key {
+    pub tonic: usize,
+    pub mode: KeyMode,
+}
+
+impl Key {
+    pub fn name(&self) -> String {
+        let mode = match self.mode {
+            KeyMode::Major => "major",
+            KeyMode::Minor => "minor",
+        };
+        format!("{} {mode}", PITCH_CLASS_NAMES[self.tonic])
+    }
+
+    /// Steps from C around the circle of fifths, 0-11. Relative major and
+    /// minor keys share a position, so neighbours are harmonically close.
+    pub fn fifths_position(&self) -> usize {
+        let major_tonic = match self.mode {
+            KeyMode::Major => self.tonic,
+            KeyMode::Minor => (self.tonic + 3) % 12,
+        };
+        major_tonic * 7 % 12
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct KeyEstimate {
+    pub key: Key,
+    /// Correlation of the recent chroma with the key's profile, 0-1.
+    pub confidence: f32,
+}
+
+/// Spectral power per pitch class, C first.
+///
+/// Only spectral peaks count, each at its parabolically interpolated
+/// frequency: low notes are closer together than the FFT bins, and the bins
+/// around a peak would otherwise leak into the neighbouring pitch classes.
+pub fn chromagram(spectrum: &Spectrum) -> [f32; 12] {
+    let mut chroma = [0.0; 12];
+    let magnitudes = spectrum.magnitudes();
+    for bin in spectrum.bin_range(CHROMA_MIN_HZ, CHROMA_MAX_HZ) {
+        let (Some(&left), Some(&right)) = (magnitudes.get(bin - 1), magnitudes.get(bin + 1)) else {
+            continue;
+        };
+        let peak = magnitudes[bin];
+        if peak <= left || peak < right {
+            continue;
+        }
+
+        let (l, c, r) = (left.max(1e-12).ln(), peak.ln(), right.max(1e-12).ln());
+        let offset = 0.5 * (l - r) / (l - 2.0 * c + r);
+        let frequency = (bin as f32 + offset) * spectrum.bin_hz();
+        // Semitones above C0
+        let semitone = (12.0 * (frequency / 440.0).log2()).round() as i32 + 57;
+        chroma[semitone.rem_euclid(12) as usize] += peak * peak;
+    }
+    chroma
+}
+
+/// Rolling key estimate from a running average of the chromagram.
+pub struct KeyEstimator {
+    chroma: [f32; 12],
+}
+
+impl KeyEstimator {
+    pub fn new() -> Self {
+        Self { chroma: [0.0; 12] }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Adds one frame's chromagram to the average, which forgets with a time
+    /// constant of `window_seconds`, and matches it against every key.
+    /// `None` while nothing tonal has been heard.
+    pub fn update(
+        &mut self,
+        chroma: &[f32; 12],
+        hop_seconds: f32,
+        window_seconds: f32,
+    ) -> Option<KeyEstimate> {
+        let smoothing = (-hop_seconds / window_seconds.max(hop_seconds)).exp();
+        for (average, &power) in self.chroma.iter_mut().zip(chroma) {
+            *average = *average * smoothing + power * (1.0 - smoothing);
+        }
+        if self.chroma.iter().sum::<f32>() <= 0.0 {
+            return None;
+        }
+
+        let mut best: Option<KeyEstimate> = None;
+        for tonic in 0..12 {
+            for (mode, profile) in [
+                (KeyMode::Major, &MAJOR_PROFILE),
+                (KeyMode::Minor, &MINOR_PROFILE),
+            ] {
+                let rotated: [f32; 12] = std::array::from_fn(|pc| profile[(pc + 12 - tonic) % 12]);
+                let confidence = correlation(&self.chroma, &rotated);
+                if best.is_none_or(|best| confidence > best.confidence) {
+                    best = Some(KeyEstimate {
+                        key: Key { tonic, mode },
+                        confidence,
+                    });
+                }
+            }
+        }
+        best.map(|estimate| KeyEstimate {
+            confidence: estimate.confidence.max(0.0),
+            ..estimate
+        })
+    }
+}
+
+/// Pearson correlation of two pitch-class vectors, 0 if either is constant.
+fn correlation(a: &[f32; 12], b: &[f32; 12]) -> f32 {
+    let mean = |v: &[f32; 12]| v.iter().sum::<f32>() / 12.0;
+    let (mean_a, mean_b) = (mean(a), mean(b));
+    let mut covariance = 0.0;
+    let mut variance_a = 0.0;
+    let mut variance_b = 0.0;
+    for (x, y) in a.iter().zip(b) {
+        covariance += (x - mean_a) * (y - mean_b);
+        variance_a += (x - mean_a).powi(2);
+        variance_b += (y - mean_b).powi(2);
+    }
+    let denominator = (variance_a * variance_b).sqrt();
+    if denominator > 0.0 {
+        covariance / denominator
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::stft::Stft;
+    use crate::config::WindowFunction;
+    use std::f32::consts::TAU;
+
+    fn chord_chroma(frequencies: &[f32]) -> [f32; 12] {
+        let samples: Vec<f32> = (0..4096)
+            .map(|n| {
+                frequencies
+                    .iter()
+                    .map(|f| (TAU * f * n as f32 / 44100.0).sin())
+                    .sum()
+            })
+            .collect();
+        let mut stft = Stft::new();
+        stft.process(&samples, WindowFunction::Hann, 44100.0);
+        chromagram(stft.spectrum())
+    }
+
+    #[test]
+    fn sine_lands_in_its_pitch_class() {
+        let chroma = chord_chroma(&[440.0]);
+        let loudest = (0..12).max_by(|&a, &b| chroma[a].total_cmp(&chroma[b]));
+        assert_eq!(loudest, Some(9), "{chroma:?}");
+    }
+
+    #[test]
+    fn chords_are_recognized_as_their_key() {
+        for (chord, expected) in [
+            ([261.63, 329.63, 392.0], "C major"),
+            ([220.0, 261.63, 329.63], "A minor"),
+            ([293.66, 369.99, 440.0], "D major"),
+        ] {
+            let chroma = chord_chroma(&chord);
+            let mut estimator = KeyEstimator::new();
+            let estimate = (0..100)
+                .map(|_| estimator.update(&chroma, 0.02, 1.0))
+                .last()
+                .flatten()
+                .unwrap();
+            assert_eq!(estimate.key.name(), expected, "{chord:?}");
+            assert!(estimate.confidence > 0.5, "{estimate:?}");
+        }
+
+        let mut estimator = KeyEstimator::new();
+        assert_eq!(estimator.update(&[0.0; 12], 0.02, 1.0), None);
+    }
+}
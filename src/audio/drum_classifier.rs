@@ -0,0 +1,151 @@
+use super::onset::{Onset, OnsetDetector, OnsetParams};
+use super::stft::Spectrum;
+
+/// Onset bands of the classifier: `(name, low_hz, high_hz)` per drum, in
+/// [`Drum::ALL`] order. Fixed rather than taken from the energy bands, which
+/// users tune for visuals.
+const DRUM_BANDS: [(&str, f32, f32); 3] = [
+    ("kick", 30.0, 150.0),
+    ("snare", 150.0, 5000.0),
+    ("hat", 5000.0, 16000.0),
+];
+
+/// Share of the frame's power increase the kick band must hold for a kick.
+/// Keeps broadband hits that merely reach into the lows from counting.
+const KICK_MIN_SHARE: f32 = 0.1;
+
+/// Centroid of the power increase above the kick band, in Hz, separating
+/// kick overtones from snares, and snares from hats.
+const SNARE_MIN_CENTROID_HZ: f32 = 500.0;
+const HAT_MIN_CENTROID_HZ: f32 = 5000.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Drum {
+    Kick,
+    Snare,
+    Hat,
+}
+
+impl Drum {
+    pub const ALL: [Drum; 3] = [Drum::Kick, Drum::Snare, Drum::Hat];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Drum::Kick => "Kick",
+            Drum::Snare => "Snare",
+            Drum::Hat => "Hat",
+        }
+    }
+}
+
+/// A classified percussion hit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DrumHit {
+    pub drum: Drum,
+    /// Stream time in seconds of the frame the hit was detected in.
+    pub time: f64,
+    /// How hard the hit stood out from its band's recent flux, 0-1.
+    pub velocity: f32,
+}
+
+/// Labels percussive onsets as kick, snare/clap or hat.
+///
+/// Each drum has its own onset band. An onset in a band only counts as that
+/// drum if the spectral shape of the frame's power increase agrees: kicks
+/// must dominate it, and the centroid of what rose above the kick band tells
+/// snares (body and crack below a few kHz) from hats (sizzle above). A kick
+/// and a snare landing together are both reported.
+pub struct DrumClassifier {
+    detector: OnsetDetector,
+    onsets: Vec<Onset>,
+    previous_power: Vec<f32>,
+    power: Vec<f32>,
+}
+
+impl DrumClassifier {
+    pub fn new() -> Self {
+        Self {
+            detector: OnsetDetector::new(),
+            onsets: Vec::new(),
+            previous_power: Vec::new(),
+            power: Vec::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Classifies one STFT frame, returning the drums hit in it.
+    pub fn process(&mut self, spectrum: &Spectrum, time: f64, params: OnsetParams) -> Vec<DrumHit> {
+        self.onsets.clear();
+        self.detector
+            .process(spectrum, time, &DRUM_BANDS, params, &mut self.onsets);
+
+        self.power.clear();
+        self.power
+            .extend(spectrum.magnitudes().iter().map(|m| m * m));
+        let shape = (self.previous_power.len() == self.power.len())
+            .then(|| RiseShape::measure(spectrum, &self.power, &self.previous_power));
+        std::mem::swap(&mut self.previous_power, &mut self.power);
+        let Some(shape) = shape else {
+            return Vec::new();
+        };
+
+        self.onsets
+            .iter()
+            .filter_map(|onset| {
+                let drum = match onset.band.as_deref()? {
+                    "kick" => Drum::Kick,
+                    "snare" => Drum::Snare,
+                    _ => Drum::Hat,
+                };
+                let confirmed = match drum {
+                    Drum::Kick => shape.kick_share >= KICK_MIN_SHARE,
+                    Drum::Snare => {
+                        (SNARE_MIN_CENTROID_HZ..HAT_MIN_CENTROID_HZ).contains(&shape.upper_centroid)
+                    }
+                    Drum::Hat => shape.upper_centroid >= HAT_MIN_CENTROID_HZ,
+                };
+                confirmed.then(|| DrumHit {
+                    drum,
+                    time,
+                    velocity: 1.0 - (-onset.strength).exp(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Where in the spectrum a frame's power increased.
+struct RiseShape {
+    /// Share of the increase inside the kick band, 0-1.
+    kick_share: f32,
+    /// Power-weighted mean frequency of the increase above the kick band, in
+    /// Hz, 0 if nothing rose there.
+    upper_centroid: f32,
+}
+
+impl RiseShape {
+    fn measure(spectrum: &Spectrum, power: &[f32], previous: &[f32]) -> Self {
+        let rise = |bin: usize| (power[bin] - previous[bin]).max(0.0);
+        let (_, kick_low, kick_high) = DRUM_BANDS[0];
+        let kick_rise: f32 = spectrum.bin_range(kick_low, kick_high).map(rise).sum();
+
+        let (mut upper_rise, mut weighted) = (0.0, 0.0);
+        for bin in spectrum.bin_range(kick_high, f32::MAX) {
+            upper_rise += rise(bin);
+            weighted += rise(bin) * bin as f32 * spectrum.bin_hz();
+        }
+
+        let total = kick_rise + upper_rise;
+        Self {
+            kick_share: if total > 0.0 { kick_rise / total } else { 0.0 },
+            upper_centroid: if upper_rise > 0.0 {
+                weighted / upper_rise
+            } else {
+                0.0
+            },
+        }
+    }
+}
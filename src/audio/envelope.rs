@@ -0,0 +1,153 @@
+use crate::config::{AudioConfig, EnvelopeSettings};
+
+/// Smoothed value and held peak of one metric.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct Envelope {
+    /// The metric after attack/release smoothing.
+    pub value: f32,
+    /// Highest recent value, held for the peak hold time and then falling at
+    /// the release rate. Equal to `value` when peak hold is off.
+    pub peak: f32,
+}
+
+/// Envelopes of the analyzer's level metrics.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct LevelEnvelopes {
+    pub loudness: Envelope,
+    pub bass_energy: Envelope,
+    pub stereo_width: Envelope,
+    pub stereo_balance: Envelope,
+    pub phase_correlation: Envelope,
+    /// In Hz, like the raw centroid.
+    pub spectral_centroid: Envelope,
+    /// Per configured band, in config order.
+    pub band_energies: Vec<Envelope>,
+}
+
+/// Raw values of the metrics in [`LevelEnvelopes`] for one frame.
+pub struct Levels<'a> {
+    pub loudness: f32,
+    pub bass_energy: f32,
+    pub stereo_width: f32,
+    pub stereo_balance: f32,
+    pub phase_correlation: f32,
+    pub spectral_centroid: f32,
+    pub band_energies: &'a [f32],
+}
+
+/// Attack/release envelope follower with optional peak hold.
+#[derive(Default)]
+pub struct EnvelopeFollower {
+    envelope: Envelope,
+    /// Time of the previous update, `None` before the first one.
+    last_time: Option<f64>,
+    /// When the held peak was last raised.
+    peak_time: f64,
+}
+
+impl EnvelopeFollower {
+    /// Follows `input` to `time`. The first update jumps straight to the input.
+    pub fn process(&mut self, time: f64, input: f32, settings: EnvelopeSettings) -> Envelope {
+        let Some(last_time) = self.last_time.replace(time) else {
+            self.envelope = Envelope {
+                value: input,
+                peak: input,
+            };
+            self.peak_time = time;
+            return self.envelope;
+        };
+        let dt = (time - last_time) as f32;
+
+        let value = &mut self.envelope.value;
+        let time_constant = if input > *value {
+            settings.attack_ms
+        } else {
+            settings.release_ms
+        };
+        *value += (input - *value) * coefficient(dt, time_constant);
+
+        let value = *value;
+        let peak = &mut self.envelope.peak;
+        if settings.peak_hold_ms <= 0.0 || value >= *peak {
+            *peak = value;
+            self.peak_time = time;
+        } else if time - self.peak_time > settings.peak_hold_ms as f64 / 1000.0 {
+            *peak += (value - *peak) * coefficient(dt, settings.release_ms);
+        }
+
+        self.envelope
+    }
+}
+
+/// Envelope followers for every metric in [`LevelEnvelopes`].
+#[derive(Default)]
+pub struct LevelFollowers {
+    loudness: EnvelopeFollower,
+    bass_energy: EnvelopeFollower,
+    stereo_width: EnvelopeFollower,
+    stereo_balance: EnvelopeFollower,
+    phase_correlation: EnvelopeFollower,
+    spectral_centroid: EnvelopeFollower,
+    band_energies: Vec<EnvelopeFollower>,
+}
+
+impl LevelFollowers {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Forgets the per-band followers, for when the band list changes.
+    pub fn reset_bands(&mut self) {
+        self.band_energies.clear();
+    }
+
+    /// Follows one frame's values with the envelope settings in `config`.
+    pub fn update(&mut self, time: f64, config: &AudioConfig, levels: Levels) -> LevelEnvelopes {
+        self.band_energies
+            .resize_with(levels.band_energies.len(), EnvelopeFollower::default);
+
+        LevelEnvelopes {
+            loudness: self
+                .loudness
+                .process(time, levels.loudness, config.loudness_envelope),
+            bass_energy: self
+                .bass_energy
+                .process(time, levels.bass_energy, config.bass_envelope),
+            stereo_width: self.stereo_width.process(
+                time,
+                levels.stereo_width,
+                config.stereo_width_envelope,
+            ),
+            stereo_balance: self.stereo_balance.process(
+                time,
+                levels.stereo_balance,
+                config.stereo_balance_envelope,
+            ),
+            phase_correlation: self.phase_correlation.process(
+                time,
+                levels.phase_correlation,
+                config.phase_correlation_envelope,
+            ),
+            spectral_centroid: self.spectral_centroid.process(
+                time,
+                levels.spectral_centroid,
+                config.spectral_centroid_envelope,
+            ),
+            band_energies: self
+                .band_energies
+                .iter_mut()
+                .zip(levels.band_energies)
+                .map(|(follower, &energy)| follower.process(time, energy, config.band_envelope))
+                .collect(),
+        }
+    }
+}
+
+/// Share of the distance to the target covered in `dt` seconds by a one-pole
+/// filter with the given time constant. A zero time constant jumps straight there.
+fn coefficient(dt: f32, time_constant_ms: f32) -> f32 {
+    if time_constant_ms <= 0.0 {
+        return 1.0;
+    }
+    1.0 - (-dt * 1000.0 / time_constant_ms).exp()
+}
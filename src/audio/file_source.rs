@@ -0,0 +1,227 @@
+use super::analyzer::AudioAnalyzer;
+use super::ring_buffer::SampleProducer;
+use super::source::AudioSource;
+use log::{debug, warn};
+use std::fs::File;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{Decoder, DecoderOptions};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// Decodes a local audio file (WAV, FLAC, MP3, OGG) and feeds it to the
+/// analyzer at playback speed, as if it were coming from a live device.
+pub struct FileSource {
+    name: String,
+    stop: Arc<AtomicBool>,
+    finished: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FileSource {
+    pub fn open(
+        path: &Path,
+        looping: bool,
+        analyzer: Arc<Mutex<AudioAnalyzer>>,
+    ) -> Result<Self, anyhow::Error> {
+        debug!("Opening audio file: {}", path.display());
+
+        let file = File::open(path)?;
+        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+        let mut hint = Hint::new();
+        if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(extension);
+        }
+
+        let probed = symphonia::default::get_probe().format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?;
+        let format = probed.format;
+
+        let track = format
+            .default_track()
+            .ok_or_else(|| anyhow::anyhow!("No audio track found"))?;
+        let track_id = track.id;
+        let sample_rate = track
+            .codec_params
+            .sample_rate
+            .ok_or_else(|| anyhow::anyhow!("Unknown sample rate"))?;
+        let channels = track
+            .codec_params
+            .channels
+            .ok_or_else(|| anyhow::anyhow!("Unknown channel layout"))?
+            .count();
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())?;
+
+        debug!("File format: sample_rate={sample_rate}, channels={channels}");
+
+        let producer = {
+            let mut analyzer = analyzer.lock().unwrap();
+            analyzer.set_format(sample_rate, channels);
+            analyzer.sample_producer()
+        };
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let finished = Arc::new(AtomicBool::new(false));
+        let playback = Playback {
+            format,
+            decoder,
+            track_id,
+            sample_rate,
+            looping,
+            producer,
+        };
+
+        let thread = {
+            let stop = stop.clone();
+            let finished = finished.clone();
+            thread::spawn(move || {
+                debug!("File playback thread started");
+                playback.run(&stop);
+                finished.store(true, Ordering::Relaxed);
+                debug!("File playback thread finished");
+            })
+        };
+
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+
+        Ok(Self {
+            name,
+            stop,
+            finished,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl AudioSource for FileSource {
+    fn label(&self) -> String {
+        format!("Playing {}", self.name)
+    }
+
+    fn is_running(&self) -> bool {
+        !self.finished.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for FileSource {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            // The panic itself was already reported on the playback thread
+            if thread.join().is_err() {
+                warn!("File playback thread panicked");
+            }
+        }
+    }
+}
+
+struct Playback {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    sample_rate: u32,
+    looping: bool,
+    producer: SampleProducer,
+}
+
+impl Playback {
+    fn run(mut self, stop: &AtomicBool) {
+        let mut sample_buffer: Option<SampleBuffer<f32>> = None;
+        let mut start = Instant::now();
+        let mut frames_sent: u64 = 0;
+
+        while !stop.load(Ordering::Relaxed) {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(e))
+                    if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+                {
+                    if self.looping && self.rewind() {
+                        start = Instant::now();
+                        frames_sent = 0;
+                        continue;
+                    }
+                    break;
+                }
+                Err(e) => {
+                    warn!("Failed to read audio packet: {e}");
+                    break;
+                }
+            };
+
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // Corrupt packets are skipped, anything else ends playback
+                Err(SymphoniaError::DecodeError(e)) => {
+                    debug!("Skipping undecodable packet: {e}");
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Failed to decode audio: {e}");
+                    break;
+                }
+            };
+
+            let frames = decoded.frames() as u64;
+            // The sample buffer counts samples, the decoded buffer frames
+            let samples = decoded.capacity() * decoded.spec().channels.count();
+            let buffer = match &mut sample_buffer {
+                Some(buffer) if buffer.capacity() >= samples => buffer,
+                _ => sample_buffer.insert(SampleBuffer::new(
+                    decoded.capacity() as u64,
+                    *decoded.spec(),
+                )),
+            };
+            buffer.copy_interleaved_ref(decoded);
+
+            // Pace output to real time so the analyzer sees a live-like stream
+            let due = Duration::from_secs_f64(frames_sent as f64 / self.sample_rate as f64);
+            if let Some(wait) = due.checked_sub(start.elapsed()) {
+                thread::sleep(wait);
+            }
+
+            self.producer.push_iter(buffer.samples().iter().copied());
+            frames_sent += frames;
+        }
+    }
+
+    fn rewind(&mut self) -> bool {
+        let seek = self.format.seek(
+            SeekMode::Accurate,
+            SeekTo::TimeStamp {
+                ts: 0,
+                track_id: self.track_id,
+            },
+        );
+        match seek {
+            Ok(_) => {
+                self.decoder.reset();
+                true
+            }
+            Err(e) => {
+                warn!("Failed to loop audio file: {e}");
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::VecDeque;
+
+use super::stft::Spectrum;
+
+/// Median filter lengths of the separation, in frames and bins.
+#[derive(Clone, Copy, Debug)]
+pub struct HpssParams {
+    /// Frames the harmonic (time) median covers, the current one included.
+    pub harmonic_frames: usize,
+    /// Bins the percussive (frequency) median covers, centered on each bin.
+    pub percussive_bins: usize,
+}
+
+/// Harmonic/percussive source separation by median filtering.
+///
+/// Sustained tones are horizontal lines in the spectrogram and survive a
+/// median across time; hits are vertical lines and survive a median across
+/// frequency. Each bin is split between the two with soft (Wiener) masks
+/// built from both medians. The time median only looks back, so a hit lands
+/// in the percussive part on the frame it starts rather than a few later.
+pub struct Hpss {
+    /// Magnitudes of the latest frames, newest last.
+    history: VecDeque<Vec<f32>>,
+    scratch: Vec<f32>,
+    percussive: Spectrum,
+}
+
+impl Hpss {
+    pub fn new() -> Self {
+        Self {
+            history: VecDeque::new(),
+            scratch: Vec::new(),
+            percussive: Spectrum::default(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Percussive part of the last processed frame.
+    pub fn percussive(&self) -> &Spectrum {
+        &self.percussive
+    }
+
+    /// Separates one STFT frame. Returns the harmonic and percussive shares
+    /// of its power, which add up to 1, or both 0 for silence.
+    pub fn process(&mut self, spectrum: &Spectrum, params: HpssParams) -> (f32, f32) {
+        let magnitudes = spectrum.magnitudes();
+        if self
+            .history
+            .back()
+            .is_some_and(|frame| frame.len() != magnitudes.len())
+        {
+            self.history.clear();
+        }
+        self.history.push_back(magnitudes.to_vec());
+        while self.history.len() > params.harmonic_frames.max(1) {
+            self.history.pop_front();
+        }
+
+        let half_width = params.percussive_bins / 2;
+        let mut percussive = Vec::with_capacity(magnitudes.len());
+        let (mut harmonic_power, mut percussive_power) = (0.0, 0.0);
+        for (bin, &magnitude) in magnitudes.iter().enumerate() {
+            self.scratch.clear();
+            self.scratch
+                .extend(self.history.iter().map(|frame| frame[bin]));
+            let harmonic = median(&mut self.scratch);
+
+            let neighbours =
+                bin.saturating_sub(half_width)..(bin + half_width + 1).min(magnitudes.len());
+            self.scratch.clear();
+            self.scratch.extend(&magnitudes[neighbours]);
+            let transient = median(&mut self.scratch);
+
+            let (h, p) = (harmonic * harmonic, transient * transient);
+            let mask = if h + p > 0.0 { p / (h + p) } else { 0.0 };
+            percussive.push(magnitude * mask);
+            // DC carries no sound of either kind
+            if bin > 0 {
+                let power = magnitude * magnitude;
+                harmonic_power += power * (1.0 - mask);
+                percussive_power += power * mask;
+            }
+        }
+        self.percussive = spectrum.with_magnitudes(percussive);
+
+        let total = harmonic_power + percussive_power;
+        if total > 0.0 {
+            (harmonic_power / total, percussive_power / total)
+        } else {
+            (0.0, 0.0)
+        }
+    }
+}
+
+fn median(values: &mut [f32]) -> f32 {
+    values.sort_by(f32::total_cmp);
+    values[values.len() / 2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::stft::Stft;
+    use crate::config::WindowFunction;
+    use std::f32::consts::TAU;
+
+    const PARAMS: HpssParams = HpssParams {
+        harmonic_frames: 9,
+        percussive_bins: 23,
+    };
+
+    fn spectrum(samples: &[f32]) -> Spectrum {
+        let mut stft = Stft::new();
+        stft.process(samples, WindowFunction::Hann, 44100.0);
+        stft.spectrum().clone()
+    }
+
+    #[test]
+    fn sustained_tones_are_harmonic_and_clicks_percussive() {
+        let tone: Vec<f32> = (0..2048)
+            .map(|n| (TAU * 440.0 * n as f32 / 44100.0).sin())
+            .collect();
+        let mut click = vec![0.0; 2048];
+        click[1024] = 1.0;
+
+        let mut hpss = Hpss::new();
+        let mut shares = (0.0, 0.0);
+        for _ in 0..PARAMS.harmonic_frames {
+            shares = hpss.process(&spectrum(&tone), PARAMS);
+        }
+        let (harmonic, percussive) = shares;
+        assert!(harmonic > 0.9, "{shares:?}");
+        assert!((harmonic + percussive - 1.0).abs() < 1e-4, "{shares:?}");
+
+        let click = spectrum(&click);
+        let shares = hpss.process(&click, PARAMS);
+        let (harmonic, percussive) = shares;
+        assert!(percussive > 0.9, "{shares:?}");
+        assert!((harmonic + percussive - 1.0).abs() < 1e-4, "{shares:?}");
+
+        // The percussive spectrum is the click, minus the tone's bins
+        let kept: f32 = hpss.percussive().magnitudes().iter().sum();
+        let original: f32 = click.magnitudes().iter().sum();
+        assert!(kept > 0.9 * original, "{kept} of {original}");
+    }
+
+    #[test]
+    fn silence_has_no_shares() {
+        let mut hpss = Hpss::new();
+        assert_eq!(hpss.process(&spectrum(&[0.0; 2048]), PARAMS), (0.0, 0.0));
+    }
+}
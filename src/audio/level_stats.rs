@@ -0,0 +1,140 @@
+use std::collections::VecDeque;
+
+/// Share of the window that must have been heard before ranks are reported.
+/// Ranked against too short a history, the loudest moments of an intro would
+/// already count as the top of the music.
+const CALIBRATION_SHARE: f64 = 0.5;
+
+/// Rank of the bass energy reported as the reference for heavy bass.
+const BASS_REFERENCE_RANK: f32 = 0.9;
+
+/// Where the current metric values sit in their recent history.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct RelativeLevels {
+    /// False until enough history has been gathered; every rank is 0 until then.
+    pub calibrated: bool,
+    pub loudness: f32,
+    pub bass_energy: f32,
+    /// Per configured band, in config order.
+    pub band_energies: Vec<f32>,
+    /// Bass energy only the top 10% of the window reach, for scaling
+    /// absolute bass levels to the music. 0 until calibrated.
+    pub bass_reference: f32,
+}
+
+/// Long-running statistics of the analyzer's level metrics.
+///
+/// Each metric is ranked against its own values over a sliding window: a rank
+/// of 0.9 means the value is at least as high as 90% of the recent history,
+/// i.e. in the top 10%. Ranks don't change with input gain or room level,
+/// only with how the music moves.
+pub struct LevelStats {
+    start: Option<f64>,
+    loudness: RollingRank,
+    bass_energy: RollingRank,
+    band_energies: Vec<RollingRank>,
+}
+
+impl LevelStats {
+    pub fn new() -> Self {
+        Self {
+            start: None,
+            loudness: RollingRank::default(),
+            bass_energy: RollingRank::default(),
+            band_energies: Vec::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Forgets the per-band histories, for when the band list changes.
+    pub fn reset_bands(&mut self) {
+        self.band_energies.clear();
+    }
+
+    /// Adds one frame's values and returns their ranks over the last
+    /// `window_seconds`.
+    pub fn update(
+        &mut self,
+        time: f64,
+        loudness: f32,
+        bass_energy: f32,
+        band_energies: &[f32],
+        window_seconds: f64,
+    ) -> RelativeLevels {
+        let start = *self.start.get_or_insert(time);
+        let calibrated = time - start >= window_seconds * CALIBRATION_SHARE;
+
+        let rank = |history: &mut RollingRank, value: f32| {
+            history.push(time, value, window_seconds);
+            if calibrated { history.rank(value) } else { 0.0 }
+        };
+        let loudness = rank(&mut self.loudness, loudness);
+        let bass_energy = rank(&mut self.bass_energy, bass_energy);
+        self.band_energies
+            .resize_with(band_energies.len(), RollingRank::default);
+        let band_energies = self
+            .band_energies
+            .iter_mut()
+            .zip(band_energies)
+            .map(|(history, &value)| rank(history, value))
+            .collect();
+        let bass_reference = if calibrated {
+            self.bass_energy.value_at(BASS_REFERENCE_RANK)
+        } else {
+            0.0
+        };
+
+        RelativeLevels {
+            calibrated,
+            loudness,
+            bass_energy,
+            band_energies,
+            bass_reference,
+        }
+    }
+}
+
+/// Values of one metric over a sliding time window.
+#[derive(Default)]
+struct RollingRank {
+    values: VecDeque<(f64, f32)>,
+    sorted: Vec<f32>,
+}
+
+impl RollingRank {
+    fn push(&mut self, time: f64, value: f32, window_seconds: f64) {
+        self.values.push_back((time, value));
+        while self
+            .values
+            .front()
+            .is_some_and(|&(t, _)| time - t > window_seconds)
+        {
+            self.values.pop_front();
+        }
+    }
+
+    /// Share of the history that `value` is at least as high as. Ties count
+    /// as below, so a level pinned at its maximum ranks 1.
+    fn rank(&self, value: f32) -> f32 {
+        if self.values.is_empty() {
+            return 0.0;
+        }
+        let at_most = self.values.iter().filter(|&&(_, v)| v <= value).count();
+        at_most as f32 / self.values.len() as f32
+    }
+
+    /// Lowest value whose rank reaches `rank`.
+    fn value_at(&mut self, rank: f32) -> f32 {
+        if self.values.is_empty() {
+            return 0.0;
+        }
+        self.sorted.clear();
+        self.sorted.extend(self.values.iter().map(|&(_, v)| v));
+        self.sorted.sort_by(f32::total_cmp);
+        let index = (rank * self.sorted.len() as f32).ceil() as usize;
+        self.sorted[index.clamp(1, self.sorted.len()) - 1]
+    }
+}
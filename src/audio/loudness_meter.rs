@@ -0,0 +1,412 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+use super::biquad::Biquad;
+
+/// Length of the sub-blocks loudness is accumulated in, in seconds. Gating
+/// blocks overlap by 75%, so one starts every sub-block.
+const SUB_BLOCK_SECONDS: f64 = 0.1;
+
+/// Sub-blocks per momentary (400 ms) and short-term (3 s) window.
+const MOMENTARY_SUB_BLOCKS: usize = 4;
+const SHORT_TERM_SUB_BLOCKS: usize = 30;
+
+/// Gating blocks quieter than this never count towards integrated loudness.
+const ABSOLUTE_GATE_LUFS: f32 = -70.0;
+
+/// Integrated loudness ignores blocks this far below the ungated average.
+const RELATIVE_GATE_LU: f32 = 10.0;
+
+/// Loudest gating block the histogram resolves, and its bin width. Louder
+/// blocks land in the top bin.
+const HISTOGRAM_MAX_LUFS: f32 = 5.0;
+const HISTOGRAM_STEP_LU: f32 = 0.1;
+
+/// Peaks are held this long, then fall at the decay rate, so short spikes
+/// stay readable however often the meter is read.
+const PEAK_HOLD_SECONDS: f32 = 1.5;
+const PEAK_DECAY_DB_PER_SECOND: f32 = 20.0;
+
+/// True-peak oversampling factor and interpolation filter taps per phase.
+const OVERSAMPLING: usize = 4;
+const INTERPOLATION_TAPS: usize = 12;
+
+/// Loudness and peak levels of the stream, per EBU R128 / ITU-R BS.1770.
+/// Every value is −∞ when there is nothing to measure yet.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MeterReading {
+    /// K-weighted loudness of the last 400 ms, in LUFS.
+    pub momentary_lufs: f32,
+    /// K-weighted loudness of the last 3 s, in LUFS.
+    pub short_term_lufs: f32,
+    /// Gated loudness of the whole stream so far, in LUFS.
+    pub integrated_lufs: f32,
+    /// Highest sample of the last 1.5 s, in dBFS. Falls at 20 dB/s once
+    /// the hold runs out.
+    pub peak_dbfs: f32,
+    /// Highest 4x oversampled level, in dBTP, held like the sample peak.
+    /// Catches inter-sample peaks a sample peak meter misses.
+    pub true_peak_dbfs: f32,
+}
+
+impl Default for MeterReading {
+    fn default() -> Self {
+        Self {
+            momentary_lufs: f32::NEG_INFINITY,
+            short_term_lufs: f32::NEG_INFINITY,
+            integrated_lufs: f32::NEG_INFINITY,
+            peak_dbfs: f32::NEG_INFINITY,
+            true_peak_dbfs: f32::NEG_INFINITY,
+        }
+    }
+}
+
+/// Loudness meter over one or two channels.
+///
+/// Samples are K-weighted and their power is summed over channels into
+/// 100 ms sub-blocks. Momentary and short-term loudness average the latest
+/// sub-blocks; integrated loudness keeps a histogram of every 400 ms gating
+/// block, so memory stays constant however long the stream runs.
+pub struct LoudnessMeter {
+    channels: Vec<MeterChannel>,
+    sub_block_frames: usize,
+    sub_block_fill: usize,
+    sub_block_power: f64,
+    /// Summed channel power of the latest completed sub-blocks, newest last.
+    sub_blocks: VecDeque<f64>,
+    /// Gating block counts per loudness bin, from the absolute gate upwards.
+    histogram: Vec<u32>,
+    peak: PeakHold,
+    true_peak: PeakHold,
+    hold_frames: usize,
+    /// Gain applied to a held peak per frame once its hold is over.
+    decay_per_frame: f32,
+    reading: MeterReading,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: f32, channels: usize) -> Self {
+        let bins = ((HISTOGRAM_MAX_LUFS - ABSOLUTE_GATE_LUFS) / HISTOGRAM_STEP_LU) as usize + 1;
+        Self {
+            channels: (0..channels.clamp(1, 2))
+                .map(|_| MeterChannel::new(sample_rate))
+                .collect(),
+            sub_block_frames: ((sample_rate as f64 * SUB_BLOCK_SECONDS) as usize).max(1),
+            sub_block_fill: 0,
+            sub_block_power: 0.0,
+            sub_blocks: VecDeque::with_capacity(SHORT_TERM_SUB_BLOCKS),
+            histogram: vec![0; bins],
+            peak: PeakHold::default(),
+            true_peak: PeakHold::default(),
+            hold_frames: (sample_rate * PEAK_HOLD_SECONDS) as usize,
+            decay_per_frame: 10f32.powf(-PEAK_DECAY_DB_PER_SECOND / 20.0 / sample_rate),
+            reading: MeterReading::default(),
+        }
+    }
+
+    /// Measures new audio, one slice per channel. Channels beyond the meter's
+    /// own are ignored. Returns the reading including these samples.
+    pub fn process(&mut self, channels: &[&[f32]]) -> MeterReading {
+        let frames = channels.first().map_or(0, |samples| samples.len());
+        let mut peak = 0.0f32;
+        let mut true_peak = 0.0f32;
+
+        for frame in 0..frames {
+            for (channel, samples) in self.channels.iter_mut().zip(channels) {
+                let sample = samples[frame];
+                peak = peak.max(sample.abs());
+                true_peak = true_peak.max(channel.true_peak(sample));
+                let weighted = channel.k_weight(sample as f64);
+                self.sub_block_power += weighted * weighted;
+            }
+
+            self.sub_block_fill += 1;
+            if self.sub_block_fill == self.sub_block_frames {
+                self.finish_sub_block();
+            }
+        }
+
+        let (hold, decay) = (self.hold_frames, self.decay_per_frame);
+        let peak = self.peak.update(peak, frames, hold, decay);
+        let true_peak = self
+            .true_peak
+            .update(true_peak.max(peak), frames, hold, decay);
+        self.reading.peak_dbfs = amplitude_to_db(peak);
+        self.reading.true_peak_dbfs = amplitude_to_db(true_peak);
+        self.reading
+    }
+
+    fn finish_sub_block(&mut self) {
+        if self.sub_blocks.len() == SHORT_TERM_SUB_BLOCKS {
+            self.sub_blocks.pop_front();
+        }
+        self.sub_blocks
+            .push_back(self.sub_block_power / self.sub_block_frames as f64);
+        self.sub_block_fill = 0;
+        self.sub_block_power = 0.0;
+
+        let momentary = self.mean_lufs(MOMENTARY_SUB_BLOCKS);
+        self.reading.momentary_lufs = momentary.unwrap_or(f32::NEG_INFINITY);
+        self.reading.short_term_lufs = self
+            .mean_lufs(SHORT_TERM_SUB_BLOCKS)
+            .unwrap_or(f32::NEG_INFINITY);
+
+        // Every completed momentary window is a gating block
+        if let Some(block) = momentary.filter(|&lufs| lufs > ABSOLUTE_GATE_LUFS) {
+            let bin = ((block - ABSOLUTE_GATE_LUFS) / HISTOGRAM_STEP_LU) as usize;
+            let last = self.histogram.len() - 1;
+            self.histogram[bin.min(last)] += 1;
+            self.reading.integrated_lufs = self.integrated_lufs();
+        }
+    }
+
+    /// Loudness of the latest `count` sub-blocks, once that many exist.
+    fn mean_lufs(&self, count: usize) -> Option<f32> {
+        let start = self.sub_blocks.len().checked_sub(count)?;
+        let power = self.sub_blocks.range(start..).sum::<f64>() / count as f64;
+        Some(power_to_lufs(power))
+    }
+
+    /// Mean of the gating blocks above the relative gate.
+    fn integrated_lufs(&self) -> f32 {
+        let bin_lufs = |bin: usize| ABSOLUTE_GATE_LUFS + (bin as f32 + 0.5) * HISTOGRAM_STEP_LU;
+        let gated_mean = |gate: f32| {
+            let (count, power) = self
+                .histogram
+                .iter()
+                .enumerate()
+                .filter(|&(bin, &count)| count > 0 && bin_lufs(bin) >= gate)
+                .fold((0u32, 0.0), |(total, power), (bin, &count)| {
+                    (
+                        total + count,
+                        power + count as f64 * lufs_to_power(bin_lufs(bin)),
+                    )
+                });
+            power_to_lufs(power / count.max(1) as f64)
+        };
+
+        let ungated = gated_mean(ABSOLUTE_GATE_LUFS);
+        gated_mean(ungated - RELATIVE_GATE_LU)
+    }
+}
+
+/// Peak level held for a while, then decaying.
+#[derive(Default)]
+struct PeakHold {
+    level: f32,
+    /// Frames since the level was last raised.
+    frames_held: usize,
+}
+
+impl PeakHold {
+    /// Takes in the peak of `frames` new frames and returns the held level.
+    fn update(
+        &mut self,
+        peak: f32,
+        frames: usize,
+        hold_frames: usize,
+        decay_per_frame: f32,
+    ) -> f32 {
+        let decaying =
+            (self.frames_held + frames).saturating_sub(hold_frames.max(self.frames_held));
+        self.frames_held += frames;
+        self.level *= decay_per_frame.powi(decaying as i32);
+        if peak >= self.level {
+            self.level = peak;
+            self.frames_held = 0;
+        }
+        self.level
+    }
+}
+
+/// Per-channel filter states of the meter.
+struct MeterChannel {
+    shelf: Biquad,
+    high_pass: Biquad,
+    /// Latest input samples for true-peak interpolation, newest first.
+    history: [f32; INTERPOLATION_TAPS],
+    /// Interpolation filter, one row of taps per oversampled phase.
+    interpolator: [[f32; INTERPOLATION_TAPS]; OVERSAMPLING],
+}
+
+impl MeterChannel {
+    fn new(sample_rate: f32) -> Self {
+        let (shelf, high_pass) = k_weighting(sample_rate as f64);
+        Self {
+            shelf,
+            high_pass,
+            history: [0.0; INTERPOLATION_TAPS],
+            interpolator: interpolation_filter(),
+        }
+    }
+
+    fn k_weight(&mut self, sample: f64) -> f64 {
+        self.high_pass.process(self.shelf.process(sample))
+    }
+
+    /// Feeds `sample` to the interpolator and returns the highest absolute
+    /// level among the oversampled points it completes.
+    fn true_peak(&mut self, sample: f32) -> f32 {
+        self.history.copy_within(0..INTERPOLATION_TAPS - 1, 1);
+        self.history[0] = sample;
+        self.interpolator
+            .iter()
+            .map(|taps| {
+                taps.iter()
+                    .zip(&self.history)
+                    .map(|(tap, x)| tap * x)
+                    .sum::<f32>()
+                    .abs()
+            })
+            .fold(0.0, f32::max)
+    }
+}
+
+/// The two stages of the BS.1770 K-weighting filter at `sample_rate`: a
+/// +4 dB high shelf modelling the head, then a high-pass removing the lows.
+fn k_weighting(sample_rate: f64) -> (Biquad, Biquad) {
+    // Shelf
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    // High-pass
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (PI * f0 / sample_rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    (shelf, high_pass)
+}
+
+/// Hann-windowed sinc interpolator. Phase `p` estimates the signal `p / 4`
+/// of a sample after the middle of the history, and each phase is normalized
+/// to unity gain at DC.
+fn interpolation_filter() -> [[f32; INTERPOLATION_TAPS]; OVERSAMPLING] {
+    let half = INTERPOLATION_TAPS as f64 / 2.0;
+    let mut filter = [[0.0; INTERPOLATION_TAPS]; OVERSAMPLING];
+    for (phase, taps) in filter.iter_mut().enumerate() {
+        let offset = phase as f64 / OVERSAMPLING as f64;
+        let raw: Vec<f64> = (0..INTERPOLATION_TAPS)
+            .map(|tap| {
+                let t = tap as f64 - half + offset;
+                let sinc = if t == 0.0 {
+                    1.0
+                } else {
+                    (PI * t).sin() / (PI * t)
+                };
+                sinc * 0.5 * (1.0 + (PI * t / half).cos())
+            })
+            .collect();
+        let sum: f64 = raw.iter().sum();
+        for (tap, value) in taps.iter_mut().zip(raw) {
+            *tap = (value / sum) as f32;
+        }
+    }
+    filter
+}
+
+fn power_to_lufs(power: f64) -> f32 {
+    (-0.691 + 10.0 * power.log10()) as f32
+}
+
+fn lufs_to_power(lufs: f32) -> f64 {
+    10f64.powf((lufs as f64 + 0.691) / 10.0)
+}
+
+fn amplitude_to_db(amplitude: f32) -> f32 {
+    20.0 * amplitude.log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    /// Meters `seconds` of a 997 Hz sine at `amplitude` in every channel.
+    fn meter_sine(amplitude: f32, channels: usize, seconds: f32) -> MeterReading {
+        let sample_rate = 44100.0;
+        let mut meter = LoudnessMeter::new(sample_rate, channels);
+        let samples: Vec<f32> = (0..(sample_rate * seconds) as usize)
+            .map(|n| amplitude * (TAU * 997.0 * n as f32 / sample_rate).sin())
+            .collect();
+        let mut reading = MeterReading::default();
+        for block in samples.chunks(512) {
+            reading = meter.process(&vec![block; channels]);
+        }
+        reading
+    }
+
+    #[test]
+    fn sine_reads_at_its_reference_level() {
+        // A 997 Hz sine in one channel reads 3.01 dB below its peak level
+        let amplitude: f32 = 0.5;
+        let peak_db = 20.0 * amplitude.log10();
+        let meter = meter_sine(amplitude, 1, 4.0);
+        for lufs in [
+            meter.momentary_lufs,
+            meter.short_term_lufs,
+            meter.integrated_lufs,
+        ] {
+            assert!((lufs - (peak_db - 3.01)).abs() < 0.1, "{meter:?}");
+        }
+        assert!((meter.peak_dbfs - peak_db).abs() < 0.05, "{meter:?}");
+        assert!(
+            (meter.peak_dbfs..meter.peak_dbfs + 0.1).contains(&meter.true_peak_dbfs),
+            "{meter:?}"
+        );
+    }
+
+    #[test]
+    fn channels_sum_by_power() {
+        let mono = meter_sine(0.5, 1, 4.0);
+        let stereo = meter_sine(0.5, 2, 4.0);
+        let gain = stereo.integrated_lufs - mono.integrated_lufs;
+        assert!((gain - 3.01).abs() < 0.1, "{stereo:?}");
+    }
+
+    #[test]
+    fn peaks_are_held_between_readings() {
+        let sample_rate = 44100.0;
+        let mut meter = LoudnessMeter::new(sample_rate, 1);
+        let mut spike = vec![0.0; 512];
+        spike[100] = 0.5;
+        meter.process(&[&spike]);
+
+        // Many quiet hops later, the spike still reads at its level
+        let quiet = vec![0.01; 512];
+        let mut reading = MeterReading::default();
+        for _ in 0..100 {
+            reading = meter.process(&[&quiet]);
+        }
+        let spike_db = amplitude_to_db(0.5);
+        assert!((reading.peak_dbfs - spike_db).abs() < 1e-3, "{reading:?}");
+        assert!(reading.true_peak_dbfs >= reading.peak_dbfs, "{reading:?}");
+
+        // Past the hold it falls, but not below the quiet signal
+        for _ in 0..100 {
+            reading = meter.process(&[&quiet]);
+        }
+        assert!(reading.peak_dbfs < spike_db - 3.0, "{reading:?}");
+        assert!(reading.peak_dbfs >= amplitude_to_db(0.01), "{reading:?}");
+    }
+
+    #[test]
+    fn silence_stays_below_the_gate() {
+        let meter = meter_sine(0.0, 2, 1.0);
+        assert_eq!(meter, MeterReading::default());
+    }
+}
@@ -0,0 +1,169 @@
+use std::f32::consts::PI;
+
+use super::stft::Spectrum;
+
+/// Lowest frequency covered by the mel filterbank, in Hz. The top is the
+/// Nyquist frequency.
+const MEL_MIN_HZ: f32 = 20.0;
+
+/// Level of mel bands without any energy, in dB.
+pub const MEL_DB_FLOOR: f32 = -120.0;
+
+/// Triangular filters spaced evenly on the mel scale.
+///
+/// Filters are rebuilt whenever the spectrum layout or the band count
+/// changes, and reused for every frame in between.
+pub struct MelFilterbank {
+    /// Bin width, bin count and band count the filters were built for.
+    layout: (f32, usize, usize),
+    /// Per band: first bin and the weights of the bins from there on.
+    filters: Vec<(usize, Vec<f32>)>,
+}
+
+impl MelFilterbank {
+    pub fn new() -> Self {
+        Self {
+            layout: (0.0, 0, 0),
+            filters: Vec::new(),
+        }
+    }
+
+    /// Power in each of `bands` mel bands of `spectrum`, in dB, low to high.
+    pub fn energies(&mut self, spectrum: &Spectrum, bands: usize) -> Vec<f32> {
+        let magnitudes = spectrum.magnitudes();
+        if magnitudes.is_empty() {
+            return vec![MEL_DB_FLOOR; bands];
+        }
+        let layout = (spectrum.bin_hz(), magnitudes.len(), bands);
+        if self.layout != layout {
+            self.build(layout);
+        }
+
+        self.filters
+            .iter()
+            .map(|(first, weights)| {
+                let power: f32 = magnitudes[*first..]
+                    .iter()
+                    .zip(weights)
+                    .map(|(m, w)| m * m * w)
+                    .sum::<f32>()
+                    / spectrum.noise_bandwidth();
+                if power > 0.0 {
+                    (10.0 * power.log10()).max(MEL_DB_FLOOR)
+                } else {
+                    MEL_DB_FLOOR
+                }
+            })
+            .collect()
+    }
+
+    fn build(&mut self, layout: (f32, usize, usize)) {
+        let (bin_hz, bins, bands) = layout;
+        let nyquist = bin_hz * (bins - 1) as f32;
+        let (low, high) = (hz_to_mel(MEL_MIN_HZ), hz_to_mel(nyquist));
+        // Band b rises from edge b, peaks at edge b + 1 and falls to edge b + 2
+        let edges: Vec<f32> = (0..bands + 2)
+            .map(|i| mel_to_hz(low + (high - low) * i as f32 / (bands + 1) as f32))
+            .collect();
+
+        self.filters = edges
+            .windows(3)
+            .map(|edge| {
+                let (lower, center, upper) = (edge[0], edge[1], edge[2]);
+                let first = (lower / bin_hz).ceil() as usize;
+                let last = ((upper / bin_hz).floor() as usize).min(bins - 1);
+                let weights = (first..=last)
+                    .map(|bin| {
+                        let frequency = bin as f32 * bin_hz;
+                        if frequency <= center {
+                            (frequency - lower) / (center - lower)
+                        } else {
+                            (upper - frequency) / (upper - center)
+                        }
+                    })
+                    .collect();
+                (first, weights)
+            })
+            .collect();
+        self.layout = layout;
+    }
+}
+
+/// The first `count` mel-frequency cepstral coefficients: the orthonormal
+/// DCT-II of the mel band levels. Coefficient 0 follows overall level,
+/// 1 the spectral tilt, and higher ones ever finer spectral shape.
+pub fn mfcc(mel_db: &[f32], count: usize) -> Vec<f32> {
+    let bands = mel_db.len() as f32;
+    (0..count.min(mel_db.len()))
+        .map(|k| {
+            let scale = if k == 0 { 1.0 } else { 2f32.sqrt() };
+            let sum: f32 = mel_db
+                .iter()
+                .enumerate()
+                .map(|(n, level)| level * (PI * k as f32 * (n as f32 + 0.5) / bands).cos())
+                .sum();
+            sum * scale / bands.sqrt()
+        })
+        .collect()
+}
+
+fn hz_to_mel(hz: f32) -> f32 {
+    2595.0 * (1.0 + hz / 700.0).log10()
+}
+
+fn mel_to_hz(mel: f32) -> f32 {
+    700.0 * (10f32.powf(mel / 2595.0) - 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::stft::Stft;
+    use crate::config::WindowFunction;
+    use std::f32::consts::TAU;
+
+    fn sine_spectrum(frequency: f32) -> Spectrum {
+        let samples: Vec<f32> = (0..2048)
+            .map(|n| (TAU * frequency * n as f32 / 44100.0).sin())
+            .collect();
+        let mut stft = Stft::new();
+        stft.process(&samples, WindowFunction::Hann, 44100.0);
+        stft.spectrum().clone()
+    }
+
+    fn loudest(levels: &[f32]) -> usize {
+        (0..levels.len())
+            .max_by(|&a, &b| levels[a].total_cmp(&levels[b]))
+            .unwrap()
+    }
+
+    #[test]
+    fn sine_peaks_in_the_mel_band_of_its_frequency() {
+        let mut filterbank = MelFilterbank::new();
+        let low = filterbank.energies(&sine_spectrum(200.0), 26);
+        let high = filterbank.energies(&sine_spectrum(4000.0), 26);
+
+        assert_eq!(low.len(), 26);
+        assert!(loudest(&low) < loudest(&high), "{low:?} {high:?}");
+        assert_eq!(
+            filterbank.energies(&Spectrum::default(), 26),
+            vec![MEL_DB_FLOOR; 26]
+        );
+    }
+
+    #[test]
+    fn mfccs_follow_level_and_tilt() {
+        // A flat spectrum only has a level
+        let flat = mfcc(&[-20.0; 16], 12);
+        assert_eq!(flat.len(), 12);
+        assert!((flat[0] + 80.0).abs() < 1e-3, "{flat:?}");
+        assert!(flat[1..].iter().all(|c| c.abs() < 1e-3), "{flat:?}");
+
+        // Falling towards the highs, like pink noise, tilts coefficient 1 up
+        let falling: Vec<f32> = (0..16).map(|band| -20.0 - band as f32).collect();
+        let rising: Vec<f32> = (0..16).map(|band| -35.0 + band as f32).collect();
+        assert!(mfcc(&falling, 2)[1] > 0.0);
+        assert!(mfcc(&rising, 2)[1] < 0.0);
+        assert_eq!(mfcc(&falling, 20).len(), 16);
+    }
+}
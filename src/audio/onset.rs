@@ -0,0 +1,216 @@
+use super::stft::Spectrum;
+use std::collections::VecDeque;
+use std::ops::Range;
+
+/// Compression applied to magnitudes before differencing: log(1 + C * |X|).
+/// Makes the flux respond to relative rather than absolute level changes.
+const LOG_COMPRESSION: f32 = 100.0;
+
+/// A detected note/hit onset.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Onset {
+    /// Stream time in seconds of the frame the onset was detected in.
+    pub time: f64,
+    /// How far the flux exceeded the adaptive threshold, relative to it.
+    pub strength: f32,
+    /// Energy band the onset was detected in, `None` for the full spectrum.
+    pub band: Option<String>,
+}
+
+/// Tuning shared by every flux channel of the detector.
+#[derive(Clone, Copy, Debug)]
+pub struct OnsetParams {
+    /// Flux must exceed the running median times this factor.
+    pub threshold: f32,
+    /// Plus this absolute offset, so silence doesn't trigger onsets.
+    pub delta: f32,
+    /// Number of past frames the running median covers.
+    pub median_frames: usize,
+    /// Minimum time between two onsets on the same channel, in seconds.
+    pub min_interval: f64,
+}
+
+/// Spectral-flux onset detection over the full spectrum and a set of bands.
+pub struct OnsetDetector {
+    previous: Vec<f32>,
+    compressed: Vec<f32>,
+    overall: FluxChannel,
+    bands: Vec<FluxChannel>,
+    novelty: f32,
+    band_novelty: Vec<f32>,
+}
+
+impl OnsetDetector {
+    pub fn new() -> Self {
+        Self {
+            previous: Vec::new(),
+            compressed: Vec::new(),
+            overall: FluxChannel::default(),
+            bands: Vec::new(),
+            novelty: 0.0,
+            band_novelty: Vec::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Forgets the per-band channels, for when the band list changes.
+    pub fn reset_bands(&mut self) {
+        self.bands.clear();
+    }
+
+    /// Full-spectrum flux of the last processed frame (the onset envelope).
+    pub fn novelty(&self) -> f32 {
+        self.novelty
+    }
+
+    /// Per-band flux of the last processed frame, in band order.
+    pub fn band_novelty(&self) -> &[f32] {
+        &self.band_novelty
+    }
+
+    /// Processes one STFT frame. `bands` lists `(name, low_hz, high_hz)` for
+    /// every per-band channel; onsets found in this frame are appended to `onsets`.
+    pub fn process(
+        &mut self,
+        spectrum: &Spectrum,
+        time: f64,
+        bands: &[(&str, f32, f32)],
+        params: OnsetParams,
+        onsets: &mut Vec<Onset>,
+    ) {
+        self.band_novelty.clear();
+        if spectrum.is_empty() {
+            self.previous.clear();
+            self.novelty = 0.0;
+            return;
+        }
+
+        self.compressed.clear();
+        self.compressed.extend(
+            spectrum
+                .magnitudes()
+                .iter()
+                .map(|m| (1.0 + LOG_COMPRESSION * m).ln()),
+        );
+
+        // First frame (or a new frame size) has nothing to compare against
+        if self.previous.len() != self.compressed.len() {
+            std::mem::swap(&mut self.previous, &mut self.compressed);
+            self.novelty = 0.0;
+            return;
+        }
+
+        let full_range = spectrum.bin_range(0.0, f32::MAX);
+        self.novelty = flux(&self.compressed, &self.previous, full_range);
+        if let Some(strength) = self.overall.detect(self.novelty, time, params) {
+            onsets.push(Onset {
+                time,
+                strength,
+                band: None,
+            });
+        }
+
+        self.bands.resize_with(bands.len(), FluxChannel::default);
+        for (channel, &(name, low_hz, high_hz)) in self.bands.iter_mut().zip(bands) {
+            let band_flux = flux(
+                &self.compressed,
+                &self.previous,
+                spectrum.bin_range(low_hz, high_hz),
+            );
+            self.band_novelty.push(band_flux);
+            if let Some(strength) = channel.detect(band_flux, time, params) {
+                onsets.push(Onset {
+                    time,
+                    strength,
+                    band: Some(name.to_string()),
+                });
+            }
+        }
+
+        std::mem::swap(&mut self.previous, &mut self.compressed);
+    }
+}
+
+/// Mean half-wave rectified increase over `bins`.
+fn flux(current: &[f32], previous: &[f32], bins: Range<usize>) -> f32 {
+    if bins.is_empty() {
+        return 0.0;
+    }
+    let count = bins.len() as f32;
+    let rise: f32 = current[bins.clone()]
+        .iter()
+        .zip(&previous[bins])
+        .map(|(c, p)| (c - p).max(0.0))
+        .sum();
+    rise / count
+}
+
+/// Adaptive-threshold peak picking for one flux signal.
+#[derive(Default)]
+struct FluxChannel {
+    history: VecDeque<f32>,
+    sorted: Vec<f32>,
+    previous_flux: f32,
+    last_onset: Option<f64>,
+}
+
+impl FluxChannel {
+    /// Returns the onset strength if this frame's flux is a rising value
+    /// above the adaptive threshold.
+    fn detect(&mut self, flux: f32, time: f64, params: OnsetParams) -> Option<f32> {
+        let threshold = self.median() * params.threshold + params.delta;
+        let rising = flux > self.previous_flux;
+        let rested = self
+            .last_onset
+            .is_none_or(|last| time - last >= params.min_interval);
+
+        self.previous_flux = flux;
+        self.history.push_back(flux);
+        while self.history.len() > params.median_frames.max(1) {
+            self.history.pop_front();
+        }
+
+        if flux > threshold && rising && rested {
+            self.last_onset = Some(time);
+            // A zero delta after silence leaves nothing to be relative to
+            Some((flux - threshold) / threshold.max(f32::EPSILON))
+        } else {
+            None
+        }
+    }
+
+    fn median(&mut self) -> f32 {
+        if self.history.is_empty() {
+            return 0.0;
+        }
+        self.sorted.clear();
+        self.sorted.extend(&self.history);
+        self.sorted.sort_by(f32::total_cmp);
+        self.sorted[self.sorted.len() / 2]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn onset_after_silence_has_finite_strength() {
+        let params = OnsetParams {
+            threshold: 1.5,
+            delta: 0.0,
+            median_frames: 8,
+            min_interval: 0.0,
+        };
+        let mut channel = FluxChannel::default();
+        for frame in 0..8 {
+            assert_eq!(channel.detect(0.0, frame as f64, params), None);
+        }
+
+        let strength = channel.detect(0.5, 8.0, params).unwrap();
+        assert!(strength.is_finite(), "strength = {strength}");
+    }
+}
@@ -0,0 +1,227 @@
+use rustfft::FftPlanner;
+use rustfft::num_complex::Complex;
+
+use super::chroma::PITCH_CLASS_NAMES;
+
+/// Fundamental frequency of the current frame.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct Pitch {
+    /// Fundamental frequency in Hz, 0 when unvoiced.
+    pub frequency: f32,
+    /// Fractional MIDI note number of the frequency (69 = A4), 0 when unvoiced.
+    pub midi_note: f32,
+    /// How periodic the frame is, 0-1. Close to 1 for a clean tone, low for
+    /// noise, chords and silence.
+    pub confidence: f32,
+    /// Whether the confidence passed the voicing threshold.
+    pub voiced: bool,
+}
+
+impl Pitch {
+    /// Nearest note with octave, e.g. "A4".
+    pub fn note_name(&self) -> String {
+        let note = self.midi_note.round() as i32;
+        format!(
+            "{}{}",
+            PITCH_CLASS_NAMES[note.rem_euclid(12) as usize],
+            note.div_euclid(12) - 1
+        )
+    }
+}
+
+/// Search range and voicing threshold of the pitch tracker.
+#[derive(Clone, Copy, Debug)]
+pub struct PitchParams {
+    pub min_freq: f32,
+    pub max_freq: f32,
+    /// Highest normalized difference that still counts as voiced.
+    pub threshold: f32,
+}
+
+/// Monophonic pitch tracker using the YIN algorithm.
+///
+/// The first half of the frame is compared with copies of the frame shifted
+/// by every candidate period. The difference function is computed through an
+/// FFT cross-correlation, so the cost doesn't grow with the period range.
+pub struct PitchTracker {
+    planner: FftPlanner<f32>,
+    signal: Vec<Complex<f32>>,
+    reference: Vec<Complex<f32>>,
+    /// Cumulative mean normalized difference per lag.
+    difference: Vec<f32>,
+}
+
+impl PitchTracker {
+    pub fn new() -> Self {
+        Self {
+            planner: FftPlanner::new(),
+            signal: Vec::new(),
+            reference: Vec::new(),
+            difference: Vec::new(),
+        }
+    }
+
+    pub fn detect(&mut self, frame: &[f32], sample_rate: f32, params: PitchParams) -> Pitch {
+        let window = frame.len() / 2;
+        let max_lag = ((sample_rate / params.min_freq) as usize).min(frame.len() - window);
+        let min_lag = ((sample_rate / params.max_freq) as usize).max(2);
+        let energy: f32 = frame[..window].iter().map(|x| x * x).sum();
+        if min_lag + 1 >= max_lag || energy <= 0.0 {
+            return Pitch::default();
+        }
+
+        self.normalized_difference(frame, window, max_lag);
+        let difference = &self.difference;
+
+        // First dip under the threshold, followed down to its minimum; the
+        // deepest dip if none reaches it
+        let lag = match (min_lag..max_lag).find(|&lag| difference[lag] < params.threshold) {
+            Some(mut lag) => {
+                while lag + 1 < max_lag && difference[lag + 1] < difference[lag] {
+                    lag += 1;
+                }
+                lag
+            }
+            None => (min_lag..max_lag)
+                .min_by(|&a, &b| difference[a].total_cmp(&difference[b]))
+                .unwrap(),
+        };
+        let confidence = (1.0 - difference[lag]).clamp(0.0, 1.0);
+        if difference[lag] >= params.threshold {
+            return Pitch {
+                confidence,
+                ..Default::default()
+            };
+        }
+
+        // Parabolic interpolation between the neighbouring lags
+        let (left, center, right) = (difference[lag - 1], difference[lag], difference[lag + 1]);
+        let curvature = left - 2.0 * center + right;
+        let offset = if curvature > 0.0 {
+            0.5 * (left - right) / curvature
+        } else {
+            0.0
+        };
+        let frequency = sample_rate / (lag as f32 + offset);
+        Pitch {
+            frequency,
+            midi_note: 69.0 + 12.0 * (frequency / 440.0).log2(),
+            confidence,
+            voiced: true,
+        }
+    }
+
+    /// Fills `self.difference` with YIN's cumulative mean normalized
+    /// difference for lags `0..=max_lag`.
+    fn normalized_difference(&mut self, frame: &[f32], window: usize, max_lag: usize) {
+        // r(lag) = sum over the window of x[j] * x[j + lag], zero-padded so
+        // the circular correlation doesn't wrap around
+        let size = (frame.len() + window).next_power_of_two();
+        self.signal.clear();
+        self.signal
+            .extend(frame.iter().map(|&x| Complex::new(x, 0.0)));
+        self.signal.resize(size, Complex::default());
+        self.reference.clear();
+        self.reference
+            .extend(frame[..window].iter().map(|&x| Complex::new(x, 0.0)));
+        self.reference.resize(size, Complex::default());
+
+        self.planner
+            .plan_fft_forward(size)
+            .process(&mut self.signal);
+        self.planner
+            .plan_fft_forward(size)
+            .process(&mut self.reference);
+        for (s, r) in self.signal.iter_mut().zip(&self.reference) {
+            *s *= r.conj();
+        }
+        self.planner
+            .plan_fft_inverse(size)
+            .process(&mut self.signal);
+        let correlation = |lag: usize| self.signal[lag].re / size as f32;
+
+        // d(lag) = energy of the window + energy of the shifted window - 2 r(lag)
+        let square = |x: &f32| x * x;
+        let energy: f32 = frame[..window].iter().map(square).sum();
+        let mut shifted_energy = energy;
+        let mut running_sum = 0.0;
+        self.difference.clear();
+        self.difference.push(1.0);
+        for lag in 1..=max_lag {
+            shifted_energy += square(&frame[lag + window - 1]) - square(&frame[lag - 1]);
+            let difference = (energy + shifted_energy - 2.0 * correlation(lag)).max(0.0);
+            running_sum += difference;
+            self.difference.push(if running_sum > 0.0 {
+                difference * lag as f32 / running_sum
+            } else {
+                1.0
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::signal_generator::SignalGenerator;
+    use crate::audio::{SignalKind, SignalSettings};
+    use std::f32::consts::TAU;
+
+    const SAMPLE_RATE: f32 = 44100.0;
+    const PARAMS: PitchParams = PitchParams {
+        min_freq: 60.0,
+        max_freq: 1200.0,
+        threshold: 0.15,
+    };
+
+    fn tone(frequencies: &[f32]) -> Vec<f32> {
+        (0..2048)
+            .map(|n| {
+                frequencies
+                    .iter()
+                    .map(|f| (TAU * f * n as f32 / SAMPLE_RATE).sin())
+                    .sum::<f32>()
+                    / frequencies.len() as f32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sine_is_heard_at_its_frequency() {
+        let pitch = PitchTracker::new().detect(&tone(&[220.0]), SAMPLE_RATE, PARAMS);
+        assert!(pitch.voiced, "{pitch:?}");
+        assert!((pitch.frequency - 220.0).abs() < 0.5, "{pitch:?}");
+        assert!((pitch.midi_note - 57.0).abs() < 0.05, "{pitch:?}");
+        assert_eq!(pitch.note_name(), "A3");
+        assert!(pitch.confidence > 0.95, "{pitch:?}");
+    }
+
+    #[test]
+    fn harmonic_tone_is_heard_at_its_fundamental() {
+        let pitch =
+            PitchTracker::new().detect(&tone(&[110.0, 220.0, 330.0, 440.0]), SAMPLE_RATE, PARAMS);
+        assert!(pitch.voiced, "{pitch:?}");
+        assert!((pitch.frequency - 110.0).abs() < 0.5, "{pitch:?}");
+    }
+
+    #[test]
+    fn noise_and_silence_are_unvoiced() {
+        let mut tracker = PitchTracker::new();
+        let mut generator = SignalGenerator::new(SignalSettings {
+            kind: SignalKind::WhiteNoise,
+            sample_rate: SAMPLE_RATE as u32,
+            ..Default::default()
+        });
+        let mut frame = vec![0.0; 2048];
+        let voiced = (0..40)
+            .filter(|_| {
+                generator.fill(&mut frame);
+                tracker.detect(&frame, SAMPLE_RATE, PARAMS).voiced
+            })
+            .count();
+        assert!(voiced < 2, "{voiced} of 40 voiced");
+
+        let silence = tracker.detect(&[0.0; 2048], SAMPLE_RATE, PARAMS);
+        assert_eq!(silence, Pitch::default());
+    }
+}
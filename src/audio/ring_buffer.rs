@@ -0,0 +1,160 @@
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread::Thread;
+
+/// Fixed-capacity single-producer/single-consumer queue of `f32` samples.
+///
+/// Samples are stored as raw bits in atomics, so both ends are lock-free and
+/// allocation-free after construction. Only one [`SampleProducer`] and one
+/// [`SampleConsumer`] may be used at a time; a second concurrent producer would
+/// corrupt the sample order (but never memory).
+struct SampleRing {
+    slots: Box<[AtomicU32]>,
+    /// Next slot to read, only advanced by the consumer.
+    head: AtomicUsize,
+    /// Next slot to write, only advanced by the producer.
+    tail: AtomicUsize,
+    /// Number of frames dropped because the ring was full.
+    overruns: AtomicUsize,
+    /// Thread unparked once enough samples are queued, so the consumer can
+    /// block instead of polling.
+    waiter: OnceLock<Thread>,
+    /// Queued samples the consumer needs before it has work to do. Pushes
+    /// below this skip the unpark, which costs a syscall on the audio thread.
+    wake_threshold: AtomicUsize,
+}
+
+/// Writing end, meant to live in the real-time audio callback.
+pub struct SampleProducer {
+    ring: Arc<SampleRing>,
+    /// Samples per interleaved frame.
+    channels: usize,
+}
+
+impl SampleProducer {
+    /// Pushes as many whole frames as fit; the rest are dropped and counted as
+    /// overruns. Never splitting a frame keeps the channels from swapping.
+    pub fn push_iter(&self, samples: impl ExactSizeIterator<Item = f32>) {
+        let ring = &*self.ring;
+        let len = ring.slots.len();
+        let head = ring.head.load(Ordering::Acquire);
+        let mut tail = ring.tail.load(Ordering::Relaxed);
+
+        let free_frames = (head + len - tail - 1) % len / self.channels;
+        let incoming_frames = samples.len() / self.channels;
+        let written_frames = incoming_frames.min(free_frames);
+
+        for sample in samples.take(written_frames * self.channels) {
+            ring.slots[tail].store(sample.to_bits(), Ordering::Relaxed);
+            tail = (tail + 1) % len;
+        }
+        ring.tail.store(tail, Ordering::Release);
+
+        if incoming_frames > written_frames {
+            ring.overruns
+                .fetch_add(incoming_frames - written_frames, Ordering::Relaxed);
+        }
+
+        // Against a possibly stale head, so this can only wake too early
+        let queued = (tail + len - head) % len;
+        if queued >= ring.wake_threshold.load(Ordering::Relaxed)
+            && let Some(waiter) = ring.waiter.get()
+        {
+            waiter.unpark();
+        }
+    }
+}
+
+/// Reading end, owned by the analyzer.
+pub struct SampleConsumer {
+    ring: Arc<SampleRing>,
+}
+
+impl SampleConsumer {
+    pub fn new(capacity: usize) -> Self {
+        // One slot stays empty to tell "full" apart from "empty"
+        let slots = (0..capacity + 1).map(|_| AtomicU32::new(0)).collect();
+        Self {
+            ring: Arc::new(SampleRing {
+                slots,
+                head: AtomicUsize::new(0),
+                tail: AtomicUsize::new(0),
+                overruns: AtomicUsize::new(0),
+                waiter: OnceLock::new(),
+                wake_threshold: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Moves every queued sample into `out`, returning how many were read.
+    pub fn pop_into(&mut self, out: &mut Vec<f32>) -> usize {
+        let ring = &*self.ring;
+        let len = ring.slots.len();
+        let tail = ring.tail.load(Ordering::Acquire);
+        let mut head = ring.head.load(Ordering::Relaxed);
+
+        let available = (tail + len - head) % len;
+        out.reserve(available);
+        while head != tail {
+            out.push(f32::from_bits(ring.slots[head].load(Ordering::Relaxed)));
+            head = (head + 1) % len;
+        }
+        ring.head.store(head, Ordering::Release);
+
+        available
+    }
+
+    /// Discards everything currently queued.
+    pub fn clear(&mut self) {
+        let tail = self.ring.tail.load(Ordering::Acquire);
+        self.ring.head.store(tail, Ordering::Release);
+    }
+
+    /// Registers the thread to unpark when samples are pushed. Only the
+    /// first registration takes effect.
+    pub fn set_waiter(&self, thread: Thread) {
+        let _ = self.ring.waiter.set(thread);
+    }
+
+    /// Only unpark the waiter once at least `samples` are queued.
+    pub fn wake_after(&self, samples: usize) {
+        self.ring.wake_threshold.store(samples, Ordering::Relaxed);
+    }
+
+    pub fn overrun_count(&self) -> usize {
+        self.ring.overruns.load(Ordering::Relaxed)
+    }
+
+    /// Creates the writing end for frames of `channels` interleaved samples.
+    /// Drop the previous producer before handing out a new one.
+    pub fn producer(&self, channels: usize) -> SampleProducer {
+        SampleProducer {
+            ring: self.ring.clone(),
+            channels: channels.max(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overruns_drop_whole_frames() {
+        let mut consumer = SampleConsumer::new(5);
+        let producer = consumer.producer(2);
+
+        producer.push_iter([1.0, -1.0, 2.0, -2.0, 3.0, -3.0].into_iter());
+        let mut out = Vec::new();
+        consumer.pop_into(&mut out);
+
+        assert_eq!(out, [1.0, -1.0, 2.0, -2.0]);
+        assert_eq!(consumer.overrun_count(), 1);
+
+        // The ring stays frame aligned for the next push
+        producer.push_iter([4.0, -4.0].into_iter());
+        out.clear();
+        consumer.pop_into(&mut out);
+        assert_eq!(out, [4.0, -4.0]);
+    }
+}
@@ -0,0 +1,401 @@
+use super::analyzer::AudioAnalyzer;
+use super::ring_buffer::SampleProducer;
+use super::source::AudioSource;
+use log::debug;
+use std::f32::consts::TAU;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Frames generated per block by [`GeneratorSource`].
+const BLOCK_FRAMES: usize = 512;
+
+/// Fixed noise seed so every run produces the same signal.
+const NOISE_SEED: u32 = 0x2545_F491;
+
+/// Cutoff below which the riser's noise sweep is removed, so a build-up
+/// carries no bass.
+const RISER_HIGHPASS_HZ: f32 = 500.0;
+
+/// Cutoffs of the drum loop's noise: snares are low-passed to keep their
+/// crack below the cymbals, hats two-pole high-passed to leave only sizzle.
+const SNARE_LOWPASS_HZ: f32 = 4000.0;
+const HAT_HIGHPASS_HZ: f32 = 7000.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SignalKind {
+    #[default]
+    Sine,
+    Sweep,
+    WhiteNoise,
+    PinkNoise,
+    KickPattern,
+    DrumLoop,
+}
+
+impl SignalKind {
+    pub const ALL: [SignalKind; 6] = [
+        SignalKind::Sine,
+        SignalKind::Sweep,
+        SignalKind::WhiteNoise,
+        SignalKind::PinkNoise,
+        SignalKind::KickPattern,
+        SignalKind::DrumLoop,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SignalKind::Sine => "Sine",
+            SignalKind::Sweep => "Sine Sweep",
+            SignalKind::WhiteNoise => "White Noise",
+            SignalKind::PinkNoise => "Pink Noise",
+            SignalKind::KickPattern => "Kick Pattern",
+            SignalKind::DrumLoop => "Drum Loop",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct SignalSettings {
+    pub kind: SignalKind,
+
+    /// Output sample rate in Hz.
+    pub sample_rate: u32,
+
+    /// Peak amplitude, 0-1.
+    pub amplitude: f32,
+
+    /// Sine frequency, and sweep start frequency, in Hz.
+    pub frequency: f32,
+
+    /// Sweep end frequency in Hz. The sweep is exponential and restarts when done.
+    pub sweep_end_frequency: f32,
+
+    /// Duration of one sweep in seconds.
+    pub sweep_seconds: f32,
+
+    /// Kick pattern and drum loop tempo.
+    pub bpm: f32,
+
+    /// Bars of quiet hats-only breakdown before each drop.
+    pub break_bars: u32,
+
+    /// Bars of kick + sub-bass drop. The break/drop cycle repeats forever.
+    pub drop_bars: u32,
+
+    /// Bars at the end of each break that build up to the drop with a noise
+    /// sweep and an accelerating snare roll. Capped at the break length.
+    pub riser_bars: u32,
+}
+
+impl Default for SignalSettings {
+    fn default() -> Self {
+        Self {
+            kind: SignalKind::default(),
+            sample_rate: 44100,
+            amplitude: 0.5,
+            frequency: 440.0,
+            sweep_end_frequency: 10000.0,
+            sweep_seconds: 10.0,
+            bpm: 128.0,
+            break_bars: 8,
+            drop_bars: 8,
+            riser_bars: 4,
+        }
+    }
+}
+
+/// Deterministic mono test-signal generator.
+///
+/// Produces the same sample sequence for the same settings, so it can drive
+/// the analyzer directly via [`AudioAnalyzer::add_samples`] without any audio
+/// hardware.
+pub struct SignalGenerator {
+    settings: SignalSettings,
+    sample_index: u64,
+    phase: f32,
+    sub_phase: f32,
+    /// Phase of the drum loop's snare body.
+    snare_phase: f32,
+    /// One-pole low-pass states of the riser's noise sweep: the sweeping
+    /// cutoff, then a fixed one whose output is removed to keep the lows out.
+    riser_filter: [f32; 2],
+    /// Two-pole filter states of the drum loop's low-passed snare and
+    /// high-passed hat noise.
+    snare_filter: [f32; 2],
+    hat_filter: [f32; 2],
+    noise_state: u32,
+    pink_state: [f32; 7],
+}
+
+impl SignalGenerator {
+    pub fn new(settings: SignalSettings) -> Self {
+        Self {
+            settings,
+            sample_index: 0,
+            phase: 0.0,
+            sub_phase: 0.0,
+            snare_phase: 0.0,
+            riser_filter: [0.0; 2],
+            snare_filter: [0.0; 2],
+            hat_filter: [0.0; 2],
+            noise_state: NOISE_SEED,
+            pink_state: [0.0; 7],
+        }
+    }
+
+    pub fn settings(&self) -> &SignalSettings {
+        &self.settings
+    }
+
+    /// Seconds of signal generated so far.
+    pub fn elapsed_seconds(&self) -> f64 {
+        self.sample_index as f64 / self.settings.sample_rate as f64
+    }
+
+    /// Whether the kick pattern is currently inside a drop section.
+    pub fn in_drop(&self) -> bool {
+        let bar = (self.elapsed_seconds() / self.bar_seconds()) as u32;
+        let cycle = self.settings.break_bars + self.settings.drop_bars;
+        cycle > 0 && bar % cycle >= self.settings.break_bars
+    }
+
+    pub fn fill(&mut self, out: &mut [f32]) {
+        for sample in out {
+            *sample = self.next_sample();
+        }
+    }
+
+    pub fn next_sample(&mut self) -> f32 {
+        let sample = match self.settings.kind {
+            SignalKind::Sine => self.oscillator(self.settings.frequency),
+            SignalKind::Sweep => self.sweep(),
+            SignalKind::WhiteNoise => self.white_noise(),
+            SignalKind::PinkNoise => self.pink_noise(),
+            SignalKind::KickPattern => self.kick_pattern(),
+            SignalKind::DrumLoop => self.drum_loop(),
+        };
+        self.sample_index += 1;
+
+        sample * self.settings.amplitude
+    }
+
+    fn oscillator(&mut self, frequency: f32) -> f32 {
+        advance_phase(&mut self.phase, frequency, self.settings.sample_rate)
+    }
+
+    fn sweep(&mut self) -> f32 {
+        let duration = self.settings.sweep_seconds.max(0.1) as f64;
+        let progress = ((self.elapsed_seconds() % duration) / duration) as f32;
+        let ratio = self.settings.sweep_end_frequency / self.settings.frequency;
+        let frequency = self.settings.frequency * ratio.powf(progress);
+        self.oscillator(frequency)
+    }
+
+    fn white_noise(&mut self) -> f32 {
+        // xorshift32
+        let mut x = self.noise_state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.noise_state = x;
+        (x as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+
+    fn pink_noise(&mut self) -> f32 {
+        // Paul Kellet's refined pink noise filter
+        let white = self.white_noise();
+        let b = &mut self.pink_state;
+        b[0] = 0.99886 * b[0] + white * 0.0555179;
+        b[1] = 0.99332 * b[1] + white * 0.0750759;
+        b[2] = 0.96900 * b[2] + white * 0.153852;
+        b[3] = 0.86650 * b[3] + white * 0.3104856;
+        b[4] = 0.55000 * b[4] + white * 0.5329522;
+        b[5] = -0.7616 * b[5] - white * 0.0168980;
+        let pink = b.iter().sum::<f32>() + white * 0.5362;
+        b[6] = white * 0.115926;
+        pink * 0.11
+    }
+
+    fn kick_pattern(&mut self) -> f32 {
+        let beat_seconds = 60.0 / self.settings.bpm.max(1.0) as f64;
+        let t = self.elapsed_seconds();
+        let since_beat = (t % beat_seconds) as f32;
+        let in_drop = self.in_drop();
+
+        // Offbeat hi-hat: short noise burst, present in both sections
+        let since_offbeat = ((t + beat_seconds / 2.0) % beat_seconds) as f32;
+        let hat = self.white_noise() * (-since_offbeat * 80.0).exp() * 0.3;
+
+        if !in_drop {
+            let riser = self.riser(t, beat_seconds);
+            return (hat + riser).clamp(-1.0, 1.0);
+        }
+
+        // Kick: pitch drops from 150 Hz to 50 Hz with a fast amplitude decay
+        let kick_freq = 50.0 + 100.0 * (-since_beat * 30.0).exp();
+        let kick = self.oscillator(kick_freq) * (-since_beat * 8.0).exp();
+
+        // Sustained sub-bass under the whole drop
+        let sub = advance_phase(&mut self.sub_phase, 55.0, self.settings.sample_rate) * 0.4;
+
+        (kick * 0.8 + sub + hat).clamp(-1.0, 1.0)
+    }
+
+    /// Four-on-the-floor kick, a snare on beats 2 and 4 and closed hats on
+    /// the offbeats: one of each drum for testing hit classification.
+    fn drum_loop(&mut self) -> f32 {
+        let beat_seconds = 60.0 / self.settings.bpm.max(1.0) as f64;
+        let t = self.elapsed_seconds();
+        let beat = (t / beat_seconds) as u64;
+        let since_beat = (t % beat_seconds) as f32;
+        let since_offbeat = ((t + beat_seconds / 2.0) % beat_seconds) as f32;
+        let coefficient =
+            |cutoff: f32| 1.0 - (-TAU * cutoff / self.settings.sample_rate as f32).exp();
+        let (snare_coefficient, hat_coefficient) =
+            (coefficient(SNARE_LOWPASS_HZ), coefficient(HAT_HIGHPASS_HZ));
+
+        let kick_freq = 50.0 + 100.0 * (-since_beat * 30.0).exp();
+        let kick = self.oscillator(kick_freq) * (-since_beat * 8.0).exp();
+
+        // Snare: a 200 Hz body under a low-passed noise crack
+        let mut crack = self.white_noise();
+        for low in &mut self.snare_filter {
+            *low += (crack - *low) * snare_coefficient;
+            crack = *low;
+        }
+        let body = advance_phase(&mut self.snare_phase, 200.0, self.settings.sample_rate);
+        let snare = if beat % 2 == 1 {
+            (body * 0.5 + crack * 3.0) * (-since_beat * 25.0).exp()
+        } else {
+            0.0
+        };
+
+        let mut hat = self.white_noise();
+        for low in &mut self.hat_filter {
+            *low += (hat - *low) * hat_coefficient;
+            hat -= *low;
+        }
+        let hat = hat * (-since_offbeat * 40.0).exp();
+
+        (kick * 0.7 + snare * 0.4 + hat).clamp(-1.0, 1.0)
+    }
+
+    /// Build-up over the last `riser_bars` of a break: noise with a low-pass
+    /// sweeping open and a snare roll going from quarter notes to sixteenths,
+    /// both swelling towards the drop.
+    fn riser(&mut self, t: f64, beat_seconds: f64) -> f32 {
+        let riser_bars = self.settings.riser_bars.min(self.settings.break_bars);
+        let cycle = self.settings.break_bars + self.settings.drop_bars;
+        if riser_bars == 0 {
+            return 0.0;
+        }
+
+        let bar = (t / self.bar_seconds()) % cycle as f64;
+        let start = (self.settings.break_bars - riser_bars) as f64;
+        if bar < start {
+            return 0.0;
+        }
+        let progress = ((bar - start) / riser_bars as f64) as f32;
+
+        let sample_rate = self.settings.sample_rate as f32;
+        let coefficient = |cutoff: f32| 1.0 - (-TAU * cutoff / sample_rate).exp();
+        let noise = self.white_noise();
+        let [swept, lows] = &mut self.riser_filter;
+        *swept += (noise - *swept) * coefficient(1000.0 * 16f32.powf(progress));
+        *lows += (*swept - *lows) * coefficient(RISER_HIGHPASS_HZ);
+        let sweep = *swept - *lows;
+
+        let hit_seconds = beat_seconds / 2f64.powi((progress * 3.0) as i32);
+        let since_hit = (t % hit_seconds) as f32;
+        let snare = self.white_noise() * (-since_hit * 40.0).exp();
+
+        sweep * (0.1 + 0.5 * progress) + snare * (0.3 + 0.5 * progress)
+    }
+
+    fn bar_seconds(&self) -> f64 {
+        4.0 * 60.0 / self.settings.bpm.max(1.0) as f64
+    }
+}
+
+/// Returns the sine at `phase` and advances it by one sample at `frequency`.
+fn advance_phase(phase: &mut f32, frequency: f32, sample_rate: u32) -> f32 {
+    let sample = phase.sin();
+    *phase = (*phase + TAU * frequency / sample_rate as f32) % TAU;
+    sample
+}
+
+/// Feeds a [`SignalGenerator`] to the analyzer in real time.
+pub struct GeneratorSource {
+    kind: SignalKind,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl GeneratorSource {
+    pub fn new(settings: SignalSettings, analyzer: Arc<Mutex<AudioAnalyzer>>) -> Self {
+        debug!("Starting signal generator: {settings:?}");
+
+        let producer = {
+            let mut analyzer = analyzer.lock().unwrap();
+            analyzer.set_format(settings.sample_rate, 1);
+            analyzer.sample_producer()
+        };
+
+        let kind = settings.kind;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = stop.clone();
+            let generator = SignalGenerator::new(settings);
+            thread::spawn(move || {
+                debug!("Signal generator thread started");
+                run_generator(generator, producer, &stop);
+                debug!("Signal generator thread finished");
+            })
+        };
+
+        Self {
+            kind,
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl AudioSource for GeneratorSource {
+    fn label(&self) -> String {
+        format!("Generating {}", self.kind.label())
+    }
+}
+
+impl Drop for GeneratorSource {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread
+                .join()
+                .expect("Failed to join signal generator thread");
+        }
+    }
+}
+
+fn run_generator(mut generator: SignalGenerator, producer: SampleProducer, stop: &AtomicBool) {
+    let mut block = [0.0; BLOCK_FRAMES];
+    let sample_rate = generator.settings().sample_rate as f64;
+    let start = Instant::now();
+    let mut frames_sent: u64 = 0;
+
+    while !stop.load(Ordering::Relaxed) {
+        generator.fill(&mut block);
+
+        // Pace output to real time so the analyzer sees a live-like stream
+        let due = Duration::from_secs_f64(frames_sent as f64 / sample_rate);
+        if let Some(wait) = due.checked_sub(start.elapsed()) {
+            thread::sleep(wait);
+        }
+
+        producer.push_iter(block.iter().copied());
+        frames_sent += BLOCK_FRAMES as u64;
+    }
+}
@@ -0,0 +1,33 @@
+/// An input that feeds interleaved samples into the analyzer's sample queue.
+///
+/// Sources configure the analyzer with their own sample rate and channel
+/// layout when created, and stop producing samples when dropped.
+pub trait AudioSource {
+    /// Short human-readable description shown in the GUI.
+    fn label(&self) -> String;
+
+    /// Whether the source is still producing samples.
+    fn is_running(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SourceKind {
+    #[default]
+    Device,
+    File,
+    Generator,
+}
+
+impl SourceKind {
+    pub const ALL: [SourceKind; 3] = [SourceKind::Device, SourceKind::File, SourceKind::Generator];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SourceKind::Device => "Audio Device",
+            SourceKind::File => "Audio File",
+            SourceKind::Generator => "Signal Generator",
+        }
+    }
+}
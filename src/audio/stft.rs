@@ -0,0 +1,216 @@
+use rustfft::{FftPlanner, num_complex::Complex};
+use std::f32::consts::TAU;
+use std::ops::Range;
+
+use crate::config::WindowFunction;
+
+/// Magnitude spectrum of one STFT frame, bins `0..=n/2`.
+///
+/// Magnitudes are scaled by the window's coherent gain, so a full-scale sine
+/// reads close to 1.0 in its peak bin regardless of window or frame size.
+#[derive(Clone, Default, Debug)]
+pub struct Spectrum {
+    magnitudes: Vec<f32>,
+    bin_hz: f32,
+    noise_bandwidth: f32,
+}
+
+impl Spectrum {
+    pub fn magnitudes(&self) -> &[f32] {
+        &self.magnitudes
+    }
+
+    /// Width of one bin in Hz.
+    pub fn bin_hz(&self) -> f32 {
+        self.bin_hz
+    }
+
+    /// Equivalent noise bandwidth of the window, in bins. Divide summed
+    /// squared magnitudes by this to get the power of the summed range.
+    pub fn noise_bandwidth(&self) -> f32 {
+        self.noise_bandwidth
+    }
+
+    /// A spectrum with this one's layout and the given magnitudes, e.g. after
+    /// masking some of its bins.
+    pub fn with_magnitudes(&self, magnitudes: Vec<f32>) -> Spectrum {
+        Spectrum {
+            magnitudes,
+            ..*self
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.magnitudes.is_empty()
+    }
+
+    /// Index of the bin containing `frequency`, clamped to the spectrum.
+    pub fn bin_for_frequency(&self, frequency: f32) -> usize {
+        if self.is_empty() {
+            return 0;
+        }
+        ((frequency / self.bin_hz) as usize).min(self.magnitudes.len() - 1)
+    }
+
+    /// Bins whose center frequency lies in `[low_hz, high_hz)`, excluding DC.
+    pub fn bin_range(&self, low_hz: f32, high_hz: f32) -> Range<usize> {
+        if self.is_empty() {
+            return 0..0;
+        }
+        let len = self.magnitudes.len();
+        let low = ((low_hz / self.bin_hz).ceil() as usize).clamp(1, len);
+        let high = ((high_hz / self.bin_hz).ceil() as usize).clamp(low, len);
+        low..high
+    }
+
+    /// Sum of magnitudes of the bins in `[low_hz, high_hz)`, excluding DC.
+    pub fn band_sum(&self, low_hz: f32, high_hz: f32) -> f32 {
+        self.magnitudes[self.bin_range(low_hz, high_hz)]
+            .iter()
+            .sum()
+    }
+}
+
+/// Windowed short-time Fourier transform with cached window and buffers.
+pub struct Stft {
+    planner: FftPlanner<f32>,
+    window_function: WindowFunction,
+    window: Vec<f32>,
+    window_gain: f32,
+    noise_bandwidth: f32,
+    scratch: Vec<Complex<f32>>,
+    spectrum: Spectrum,
+}
+
+impl Stft {
+    pub fn new() -> Self {
+        Self {
+            planner: FftPlanner::new(),
+            window_function: WindowFunction::default(),
+            window: Vec::new(),
+            window_gain: 0.0,
+            noise_bandwidth: 1.0,
+            scratch: Vec::new(),
+            spectrum: Spectrum::default(),
+        }
+    }
+
+    /// Transforms one frame of samples and caches the resulting spectrum.
+    pub fn process(&mut self, frame: &[f32], window_function: WindowFunction, sample_rate: f32) {
+        let size = frame.len();
+        if size < 2 {
+            self.clear();
+            return;
+        }
+
+        if self.window.len() != size || self.window_function != window_function {
+            self.window = window_function.coefficients(size);
+            self.window_gain = self.window.iter().sum();
+            let power: f32 = self.window.iter().map(|w| w * w).sum();
+            self.noise_bandwidth = size as f32 * power / (self.window_gain * self.window_gain);
+            self.window_function = window_function;
+        }
+
+        self.scratch.clear();
+        self.scratch.extend(
+            frame
+                .iter()
+                .zip(&self.window)
+                .map(|(&x, &w)| Complex::new(x * w, 0.0)),
+        );
+
+        let fft = self.planner.plan_fft_forward(size);
+        fft.process(&mut self.scratch);
+
+        // Single-sided amplitude spectrum
+        let scale = 2.0 / self.window_gain;
+        self.spectrum.magnitudes.clear();
+        self.spectrum
+            .magnitudes
+            .extend(self.scratch[..=size / 2].iter().map(|c| c.norm() * scale));
+        self.spectrum.bin_hz = sample_rate / size as f32;
+        self.spectrum.noise_bandwidth = self.noise_bandwidth;
+    }
+
+    /// Forgets the cached spectrum, e.g. while the analysis window is still filling.
+    pub fn clear(&mut self) {
+        self.spectrum.magnitudes.clear();
+    }
+
+    pub fn spectrum(&self) -> &Spectrum {
+        &self.spectrum
+    }
+}
+
+impl WindowFunction {
+    /// Periodic window coefficients for a frame of `size` samples.
+    pub fn coefficients(&self, size: usize) -> Vec<f32> {
+        // Generalized cosine windows: w(n) = sum_k (-1)^k a_k cos(2 pi k n / N)
+        let terms: &[f32] = match self {
+            WindowFunction::Hann => &[0.5, 0.5],
+            WindowFunction::Hamming => &[0.54, 0.46],
+            WindowFunction::BlackmanHarris => &[0.35875, 0.48829, 0.14128, 0.01168],
+            WindowFunction::FlatTop => &[
+                0.215_578_95,
+                0.416_631_58,
+                0.277_263_16,
+                0.083_578_95,
+                0.006_947_37,
+            ],
+        };
+
+        (0..size)
+            .map(|n| {
+                let phase = TAU * n as f32 / size as f32;
+                terms
+                    .iter()
+                    .enumerate()
+                    .map(|(k, a)| {
+                        let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+                        sign * a * (phase * k as f32).cos()
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frequency: f32, sample_rate: f32, size: usize) -> Vec<f32> {
+        (0..size)
+            .map(|n| (TAU * frequency * n as f32 / sample_rate).sin())
+            .collect()
+    }
+
+    #[test]
+    fn flat_top_reads_full_scale_sine_amplitude() {
+        let mut stft = Stft::new();
+        stft.process(
+            &sine(1000.0, 48000.0, 4096),
+            WindowFunction::FlatTop,
+            48000.0,
+        );
+
+        let peak = stft
+            .spectrum()
+            .magnitudes
+            .iter()
+            .copied()
+            .fold(0.0, f32::max);
+        assert!((peak - 1.0).abs() < 0.01, "peak = {peak}");
+    }
+
+    #[test]
+    fn hann_window_keeps_sine_energy_out_of_the_bass_band() {
+        let mut stft = Stft::new();
+        stft.process(&sine(440.0, 44100.0, 2048), WindowFunction::Hann, 44100.0);
+
+        let spectrum = stft.spectrum();
+        let bass = spectrum.band_sum(0.0, 250.0);
+        let total = spectrum.band_sum(0.0, 22050.0);
+        assert!(bass / total < 0.01, "bass share = {}", bass / total);
+    }
+}
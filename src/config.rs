@@ -1,52 +1,508 @@
 pub const APP_VERSION: &str = "v0.0.1";
 
+/// How interleaved multi-channel input is folded down to the mono signal
+/// the analyzer works on.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ChannelMode {
+    /// Average of all channels.
+    #[default]
+    MonoSum,
+    /// First channel only.
+    Left,
+    /// Second channel only (falls back to the first on mono devices).
+    Right,
+    /// Loudest channel per sample frame.
+    Max,
+}
+
+impl ChannelMode {
+    pub const ALL: [ChannelMode; 4] = [
+        ChannelMode::MonoSum,
+        ChannelMode::Left,
+        ChannelMode::Right,
+        ChannelMode::Max,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChannelMode::MonoSum => "Mono (sum)",
+            ChannelMode::Left => "Left only",
+            ChannelMode::Right => "Right only",
+            ChannelMode::Max => "Max",
+        }
+    }
+}
+
+/// Analysis window applied to each STFT frame before the FFT.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum WindowFunction {
+    /// Good general-purpose tradeoff between leakage and resolution.
+    #[default]
+    Hann,
+    /// Slightly narrower main lobe than Hann, higher far sidelobes.
+    Hamming,
+    /// Very low leakage, wider main lobe.
+    BlackmanHarris,
+    /// Most accurate peak amplitudes, poorest frequency resolution.
+    FlatTop,
+}
+
+impl WindowFunction {
+    pub const ALL: [WindowFunction; 4] = [
+        WindowFunction::Hann,
+        WindowFunction::Hamming,
+        WindowFunction::BlackmanHarris,
+        WindowFunction::FlatTop,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            WindowFunction::Hann => "Hann",
+            WindowFunction::Hamming => "Hamming",
+            WindowFunction::BlackmanHarris => "Blackman-Harris",
+            WindowFunction::FlatTop => "Flat-top",
+        }
+    }
+}
+
+/// Frequency axis used to space the bands of the spectrum display.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum FrequencyScale {
+    /// Equal width per octave.
+    #[default]
+    Log,
+    /// Critical bands of hearing.
+    Bark,
+    /// Equivalent rectangular bandwidths, close to Bark but finer in the bass.
+    Erb,
+}
+
+impl FrequencyScale {
+    pub const ALL: [FrequencyScale; 3] = [
+        FrequencyScale::Log,
+        FrequencyScale::Bark,
+        FrequencyScale::Erb,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FrequencyScale::Log => "Logarithmic",
+            FrequencyScale::Bark => "Bark",
+            FrequencyScale::Erb => "ERB",
+        }
+    }
+}
+
+/// Number of bars in a musical phrase.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PhraseLength {
+    Bars8,
+    /// The usual length between changes in dance music.
+    #[default]
+    Bars16,
+    Bars32,
+}
+
+impl PhraseLength {
+    pub const ALL: [PhraseLength; 3] = [
+        PhraseLength::Bars8,
+        PhraseLength::Bars16,
+        PhraseLength::Bars32,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PhraseLength::Bars8 => "8 bars",
+            PhraseLength::Bars16 => "16 bars",
+            PhraseLength::Bars32 => "32 bars",
+        }
+    }
+
+    pub fn bars(&self) -> usize {
+        match self {
+            PhraseLength::Bars8 => 8,
+            PhraseLength::Bars16 => 16,
+            PhraseLength::Bars32 => 32,
+        }
+    }
+}
+
+/// Attack/release smoothing and peak hold of one metric's envelope.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct EnvelopeSettings {
+    /// Time constant while the metric rises, in milliseconds.
+    /// Lower = follows hits instantly, Higher = softer swells
+    pub attack_ms: f32,
+
+    /// Time constant while the metric falls, in milliseconds.
+    /// Lower = drops back between hits, Higher = smooth tails
+    pub release_ms: f32,
+
+    /// How long the peak stays at its highest value before it falls at the
+    /// release rate, in milliseconds. 0 = no peak hold.
+    pub peak_hold_ms: f32,
+}
+
+impl EnvelopeSettings {
+    pub fn new(attack_ms: f32, release_ms: f32, peak_hold_ms: f32) -> Self {
+        Self {
+            attack_ms,
+            release_ms,
+            peak_hold_ms,
+        }
+    }
+}
+
+/// How the drop detection thresholds are interpreted.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ThresholdMode {
+    /// Thresholds are absolute bass energy and loudness levels.
+    #[default]
+    Absolute,
+    /// Thresholds are ranks in the recent history: 0.9 = top 10% of the
+    /// adaptive window. Calibrates itself to the room, device and genre.
+    Adaptive,
+}
+
+impl ThresholdMode {
+    pub const ALL: [ThresholdMode; 2] = [ThresholdMode::Absolute, ThresholdMode::Adaptive];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ThresholdMode::Absolute => "Absolute",
+            ThresholdMode::Adaptive => "Adaptive",
+        }
+    }
+}
+
+/// Response of one pre-analysis filter stage.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum FilterKind {
+    /// Removes everything below the frequency, e.g. rumble and handling noise.
+    #[default]
+    HighPass,
+    /// Removes everything above the frequency.
+    LowPass,
+    /// Keeps a band around the frequency, Q sets its width.
+    BandPass,
+    /// Boosts or cuts everything below the frequency.
+    LowShelf,
+    /// Boosts or cuts everything above the frequency.
+    HighShelf,
+    /// Removes a narrow band around the frequency, e.g. mains or HVAC hum.
+    Notch,
+}
+
+impl FilterKind {
+    pub const ALL: [FilterKind; 6] = [
+        FilterKind::HighPass,
+        FilterKind::LowPass,
+        FilterKind::BandPass,
+        FilterKind::LowShelf,
+        FilterKind::HighShelf,
+        FilterKind::Notch,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FilterKind::HighPass => "High-pass",
+            FilterKind::LowPass => "Low-pass",
+            FilterKind::BandPass => "Band-pass",
+            FilterKind::LowShelf => "Low shelf",
+            FilterKind::HighShelf => "High shelf",
+            FilterKind::Notch => "Notch",
+        }
+    }
+
+    /// Whether the stage's gain setting does anything.
+    pub fn has_gain(&self) -> bool {
+        matches!(self, FilterKind::LowShelf | FilterKind::HighShelf)
+    }
+}
+
+/// One biquad filter in the pre-analysis chain.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FilterStage {
+    pub kind: FilterKind,
+
+    /// Cutoff, center or shelf frequency in Hz.
+    pub frequency: f32,
+
+    /// Resonance of pass filters, width of band-pass and notch filters and
+    /// slope of shelves. 0.707 = flat pass band.
+    /// Higher = sharper, Lower = gentler
+    pub q: f32,
+
+    /// Shelf boost (positive) or cut (negative) in dB.
+    pub gain_db: f32,
+
+    /// Bypassed stages are kept but don't touch the signal.
+    pub enabled: bool,
+}
+
+impl FilterStage {
+    pub fn new(kind: FilterKind, frequency: f32, q: f32, gain_db: f32) -> Self {
+        Self {
+            kind,
+            frequency,
+            q,
+            gain_db,
+            enabled: true,
+        }
+    }
+}
+
+/// A named frequency range tracked as its own energy value.
 #[derive(Clone, PartialEq, Debug)]
-pub struct AudioConfig {
-    /// Sample rate in Hz. Standard CD quality is 44100 Hz.
-    /// Higher = better frequency resolution but more CPU usage.
-    pub sample_rate: f32,
+pub struct EnergyBand {
+    pub name: String,
+
+    /// Frequency range in Hz.
+    pub min_freq: f32,
+    pub max_freq: f32,
+
+    /// Multiplier to amplify this band's share of the total energy.
+    /// Higher = more sensitive, Lower = only strong content shows high values
+    pub multiplier: f32,
 
+    /// Exponential smoothing factor, 0-1.
+    /// 0 = raw per-hop values, closer to 1 = slower, steadier readings
+    pub smoothing: f32,
+
+    /// Whether this band contributes to `bass_energy` (and so to drop detection).
+    pub bass: bool,
+}
+
+impl EnergyBand {
+    pub fn new(name: &str, min_freq: f32, max_freq: f32, smoothing: f32, bass: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            min_freq,
+            max_freq,
+            multiplier: 2.5,
+            smoothing,
+            bass,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct AudioConfig {
     /// Number of samples to analyze at once. Must be a power of 2 for FFT.
     /// Larger = better frequency resolution but more latency.
     /// 2048 samples at 44.1kHz = ~46ms of audio
     pub buffer_size: usize,
 
-    /// Maximum frequency (in Hz) considered as "bass".
-    /// Typical ranges: Sub-bass (20-60Hz), Bass (60-250Hz)
-    /// Lower values = only deep bass, Higher values = include more mid-bass
-    pub bass_freq_max: f32,
+    /// Window applied to each analysis frame to reduce spectral leakage.
+    pub window_function: WindowFunction,
+
+    /// Named frequency bands, each reported as its own energy value.
+    /// Bands marked as bass are summed into the bass energy used for drops.
+    pub energy_bands: Vec<EnergyBand>,
 
-    /// Multiplier to amplify bass energy readings.
-    /// Higher = more sensitive to bass (drops will trigger easier)
-    /// Lower = only very heavy bass will show high values
-    pub bass_energy_multiplier: f32,
+    /// Envelope followers of loudness, bass energy, the stereo field, the
+    /// spectral centroid and every energy band. Raw values stay available
+    /// next to the envelopes.
+    pub loudness_envelope: EnvelopeSettings,
+    pub bass_envelope: EnvelopeSettings,
+    pub stereo_width_envelope: EnvelopeSettings,
+    pub stereo_balance_envelope: EnvelopeSettings,
+    pub phase_correlation_envelope: EnvelopeSettings,
+    pub spectral_centroid_envelope: EnvelopeSettings,
+    pub band_envelope: EnvelopeSettings,
 
-    /// Multiplier to amplify bass energy readings.
-    /// Higher = more sensitive to bass (drops will trigger easier)
-    /// Lower = only very heavy bass will show high values
+    /// Whether the drop thresholds below are absolute levels or ranks in the
+    /// recent history.
+    pub threshold_mode: ThresholdMode,
+
+    /// Length of the history adaptive thresholds rank against, in seconds.
+    /// Longer = steadier calibration, Shorter = adapts faster to a new track
+    pub adaptive_window_seconds: f32,
+
+    /// Bass energy that starts a drop.
+    /// Higher = only very heavy bass triggers a drop
+    /// Lower = drops trigger easier
     pub drop_detection_threshold: f32,
 
+    /// Bass energy below which a running drop starts to end. Keep it below
+    /// the detection threshold so a drop doesn't flicker on and off.
+    pub drop_exit_threshold: f32,
+
+    /// Loudness needed for a build-up or a drop, 0-1.
+    pub drop_loudness_gate: f32,
+
+    /// Minimum length of a drop in seconds, whatever the levels do.
+    pub drop_hold_seconds: f32,
+
+    /// How long bass or loudness must stay below their thresholds before a
+    /// drop ends, in seconds. Bridges the gaps between kicks.
+    pub drop_release_seconds: f32,
+
+    /// Time after a drop ends before another one can start, in seconds.
+    pub drop_cooldown_seconds: f32,
+
+    /// How far back build-up trends (rising centroid, highs and snare
+    /// density) are measured, in seconds.
+    /// Longer = steadier, but long risers are recognized later
+    /// Shorter = reacts faster, but short sweeps and fills count too
+    pub buildup_window_seconds: f32,
+
+    /// Splits every frame into a harmonic (sustained) and a percussive
+    /// (transient) part by median filtering the spectrogram.
+    pub hpss_enabled: bool,
+
+    /// Length of the time median that keeps sustained sound, in seconds.
+    /// Longer = only very steady tones count as harmonic
+    /// Shorter = reacts faster to chord changes, but long hits count as harmonic
+    pub hpss_harmonic_seconds: f32,
+
+    /// Width of the frequency median that keeps transients, in Hz.
+    /// Wider = only broadband hits count as percussive
+    pub hpss_percussive_hz: f32,
+
+    /// Whether onset, drum and beat detection only hear the percussive part,
+    /// so sustained sub-bass and pads don't mask the hits. Needs HPSS enabled.
+    pub percussive_onsets: bool,
+
+    /// Spectral flux must exceed its running median times this factor to
+    /// count as an onset.
+    /// Higher = only strong hits, Lower = more onsets (and more false ones)
+    pub onset_threshold: f32,
+
+    /// Absolute flux added to the onset threshold, keeps noise and near
+    /// silence from producing onsets.
+    pub onset_delta: f32,
+
+    /// Length of the running median the onset threshold adapts to, in seconds.
+    pub onset_median_seconds: f32,
+
+    /// Minimum time between two onsets in the same band, in milliseconds.
+    pub onset_min_interval_ms: f32,
+
+    /// Tempo range the beat tracker searches, in BPM.
+    /// Narrower = fewer half/double tempo mistakes, but tempos outside are missed
+    pub tempo_min_bpm: f32,
+    pub tempo_max_bpm: f32,
+
+    /// Bars per phrase for the phrase counter. Drops usually land on the
+    /// first bar of a phrase.
+    pub phrase_length: PhraseLength,
+
+    /// Frequency range the pitch tracker searches for a fundamental, in Hz.
+    /// Narrower = fewer octave mistakes, but notes outside are missed
+    pub pitch_min_freq: f32,
+    pub pitch_max_freq: f32,
+
+    /// How periodic a frame must be to count as a pitched note, 0-1 (YIN's
+    /// normalized difference, so lower is stricter).
+    /// Higher = more notes, but noise and chords get a pitch too
+    /// Lower = only clean, steady tones are tracked
+    pub pitch_threshold: f32,
+
+    /// How much recent harmony the key estimate averages over, in seconds.
+    /// Longer = steadier key, Shorter = follows key changes faster
+    pub key_window_seconds: f32,
+
+    /// Number of bands in the spectrum sent to the GUI, visuals and controller.
+    pub spectrum_bands: usize,
+
+    /// How spectrum bands are spaced along the frequency axis.
+    pub spectrum_scale: FrequencyScale,
+
+    /// Lowest and highest frequency (in Hz) covered by the spectrum bands.
+    /// The upper limit is capped at the stream's Nyquist frequency.
+    pub spectrum_min_freq: f32,
+    pub spectrum_max_freq: f32,
+
+    /// Level (in dBFS) mapped to 0 in the normalized spectrum.
+    /// Lower = quieter details show up, Higher = only strong peaks show up
+    pub spectrum_db_floor: f32,
+
+    /// Number of mel filterbank bands computed per frame.
+    pub mel_bands: usize,
+
+    /// Number of MFCCs derived from the mel bands, capped at the band count.
+    /// More = finer timbre detail, the first 13 or so are usually enough
+    pub mfcc_coefficients: usize,
+
+    /// Filters the input passes through, in order, before any analysis.
+    /// Only the analysis hears them; the source itself is untouched.
+    pub pre_filters: Vec<FilterStage>,
+
+    /// Gain applied after the pre-filters, in dB.
+    pub pre_gain_db: f32,
+
     /// Multiplier for overall loudness calculation.
     /// Higher = more sensitive to quiet sounds
     /// Lower = only loud sounds register high values
     pub loudness_multiplier: f32,
 
-    /// Update interval in milliseconds for audio processing.
+    /// How stereo (or multi-channel) input is downmixed before analysis.
+    pub channel_mode: ChannelMode,
+
+    /// Number of new samples that trigger one analysis step.
     /// Lower = more frequent updates, more CPU usage
     /// Higher = less frequent updates, more latency
-    pub update_interval_ms: u64,
+    /// 1024 samples at 44.1kHz = ~23ms between updates
+    pub hop_size: usize,
 }
 
 impl Default for AudioConfig {
     fn default() -> Self {
         Self {
-            sample_rate: 44100.0,
             buffer_size: 2048,
-            bass_freq_max: 250.0,
-            bass_energy_multiplier: 2.5,
+            window_function: WindowFunction::default(),
+            energy_bands: vec![
+                EnergyBand::new("sub", 20.0, 60.0, 0.0, true),
+                EnergyBand::new("kick", 60.0, 250.0, 0.0, true),
+                EnergyBand::new("low-mid", 250.0, 500.0, 0.3, false),
+                EnergyBand::new("mid", 500.0, 2000.0, 0.3, false),
+                EnergyBand::new("presence", 2000.0, 6000.0, 0.3, false),
+                EnergyBand::new("air", 6000.0, 20000.0, 0.3, false),
+            ],
+            loudness_envelope: EnvelopeSettings::new(10.0, 250.0, 500.0),
+            bass_envelope: EnvelopeSettings::new(10.0, 250.0, 0.0),
+            stereo_width_envelope: EnvelopeSettings::new(50.0, 500.0, 0.0),
+            stereo_balance_envelope: EnvelopeSettings::new(50.0, 500.0, 0.0),
+            phase_correlation_envelope: EnvelopeSettings::new(50.0, 500.0, 0.0),
+            spectral_centroid_envelope: EnvelopeSettings::new(20.0, 300.0, 0.0),
+            band_envelope: EnvelopeSettings::new(5.0, 200.0, 0.0),
+            threshold_mode: ThresholdMode::default(),
+            adaptive_window_seconds: 60.0,
             drop_detection_threshold: 0.8,
+            drop_exit_threshold: 0.5,
+            drop_loudness_gate: 0.7,
+            drop_hold_seconds: 4.0,
+            drop_release_seconds: 1.0,
+            drop_cooldown_seconds: 4.0,
+            buildup_window_seconds: 4.0,
+            hpss_enabled: false,
+            hpss_harmonic_seconds: 0.2,
+            hpss_percussive_hz: 500.0,
+            percussive_onsets: true,
+            onset_threshold: 1.5,
+            onset_delta: 0.05,
+            onset_median_seconds: 0.5,
+            onset_min_interval_ms: 50.0,
+            tempo_min_bpm: 70.0,
+            tempo_max_bpm: 180.0,
+            phrase_length: PhraseLength::default(),
+            pitch_min_freq: 60.0,
+            pitch_max_freq: 1200.0,
+            pitch_threshold: 0.15,
+            key_window_seconds: 10.0,
+            spectrum_bands: 32,
+            spectrum_scale: FrequencyScale::default(),
+            spectrum_min_freq: 20.0,
+            spectrum_max_freq: 20000.0,
+            spectrum_db_floor: -80.0,
+            mel_bands: 40,
+            mfcc_coefficients: 13,
+            pre_filters: Vec::new(),
+            pre_gain_db: 0.0,
             loudness_multiplier: 10.0,
-            update_interval_ms: 50,
+            channel_mode: ChannelMode::default(),
+            hop_size: 1024,
         }
     }
 }
@@ -1,28 +1,267 @@
-use crate::{audio::AudioMetrics, config::AudioConfig};
+mod bar_tracker;
+mod buildup_detector;
+mod drop_detector;
+
+use crate::audio::{AudioMetrics, BandEnergy, Drum, KeyEstimate, LevelEnvelopes, Pitch, Tempo};
+use crate::config::{AudioConfig, ThresholdMode};
+use bar_tracker::BarTracker;
+use buildup_detector::{BuildupCues, BuildupDetector};
+use drop_detector::{DropDetector, DropSettings};
 use std::sync::{Arc, RwLock};
 
+pub use bar_tracker::{BEATS_PER_BAR, BarPosition};
+pub use drop_detector::DropState;
+
+/// Energy bands starting at or above this frequency count as highs for
+/// build-up detection.
+const HIGH_BAND_MIN_FREQ: f32 = 2000.0;
+
+/// Build-up intensity that puts the drop state machine into build-up.
+const BUILDUP_STATE_THRESHOLD: f32 = 0.5;
+
+/// Build-up intensity above which a time to drop is predicted.
+const BUILDUP_PREDICTION_THRESHOLD: f32 = 0.3;
+
+/// Spectral centroids mapped to brightness 0 and 1; brightness is linear in
+/// octaves between them.
+const DARK_CENTROID_HZ: f32 = 200.0;
+const BRIGHT_CENTROID_HZ: f32 = 8000.0;
+
+/// Spectral flatness of white noise's spectrum (e^-γ, as each bin's power
+/// is exponentially distributed), mapped to noisiness 1.
+const NOISE_FLATNESS: f32 = 0.56;
+
+/// Discrete happenings the controller reports once, on the frame they occur.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ControllerEvent {
+    DropStarted {
+        time: f64,
+    },
+    DropEnded {
+        time: f64,
+    },
+    /// A predicted beat, for stepping beat-synced effects.
+    Beat {
+        time: f64,
+        bpm: f32,
+        /// Set on the first beat of a bar.
+        downbeat: bool,
+    },
+    /// A classified drum hit, so each drum can drive its own light.
+    Hit {
+        time: f64,
+        drum: Drum,
+        velocity: f32,
+    },
+    /// An onset, for flashing on individual hits.
+    Onset {
+        time: f64,
+        /// Index into the output's band energies of the band the onset was
+        /// detected in, `None` for the full spectrum.
+        band: Option<usize>,
+    },
+}
+
 #[derive(Clone, Default, Debug)]
 pub struct ControllerOutput {
-    pub is_drop: bool,
+    /// Current phase of the drop cycle.
+    pub drop_state: DropState,
+    /// Events raised by this frame. Taken out before the output is shared;
+    /// the GUI receives them through a queue instead, so none go missing.
+    pub events: Vec<ControllerEvent>,
     pub loudness: f32,
+    pub stereo_width: f32,
+    /// Smoothed and peak-held loudness, bass, width and band energies.
+    pub envelopes: LevelEnvelopes,
+    /// Normalized band levels, low to high frequency.
+    pub spectrum: Vec<f32>,
+    /// Energy of every configured band, for driving individual lights.
+    pub band_energies: Vec<BandEnergy>,
+    /// Tempo and beat phase, for beat-synced animation.
+    pub tempo: Tempo,
+    /// Position of the latest beat in its bar and phrase.
+    pub bar_position: BarPosition,
+    /// Pitch of the lead melody or vocal, while one is heard.
+    pub pitch: Option<Pitch>,
+    /// Key of the recent music and how clearly it stands out, for picking
+    /// a harmonic color palette.
+    pub key: Option<KeyEstimate>,
+    /// Spectral centroid on a 0-1 scale: 0 = dark and bassy, 1 = bright.
+    pub brightness: f32,
+    /// Spectral flatness on a 0-1 scale: 0 = tonal, 1 = white noise.
+    pub noisiness: f32,
+    /// How much the music sounds like a pre-drop build-up, 0-1.
+    pub buildup_intensity: f32,
+    /// Predicted seconds until the drop, assuming it lands on the next phrase
+    /// start. Only set while a build-up is going on and the tempo is known.
+    pub time_to_drop: Option<f32>,
 }
 
 pub struct Controller {
     config: Arc<RwLock<AudioConfig>>,
+    bar_tracker: BarTracker,
+    buildup_detector: BuildupDetector,
+    drop_detector: DropDetector,
 }
 
 impl Controller {
     pub fn new(config: Arc<RwLock<AudioConfig>>) -> Self {
-        Self { config }
+        Self {
+            config,
+            bar_tracker: BarTracker::new(),
+            buildup_detector: BuildupDetector::new(),
+            drop_detector: DropDetector::new(),
+        }
     }
 
-    pub fn process(&self, metrics: AudioMetrics) -> ControllerOutput {
-        let threshold = self.config.read().unwrap().drop_detection_threshold;
-        let is_drop = metrics.bass_energy > threshold && metrics.loudness > 0.7;
+    pub fn process(&mut self, metrics: AudioMetrics) -> ControllerOutput {
+        let (drop_settings, threshold_mode, phrase_length, buildup_window, high_energy) = {
+            let config = self.config.read().unwrap();
+            let settings = DropSettings {
+                enter_threshold: config.drop_detection_threshold,
+                exit_threshold: config.drop_exit_threshold,
+                loudness_gate: config.drop_loudness_gate,
+                buildup_threshold: BUILDUP_STATE_THRESHOLD,
+                hold_seconds: config.drop_hold_seconds as f64,
+                release_seconds: config.drop_release_seconds as f64,
+                cooldown_seconds: config.drop_cooldown_seconds as f64,
+            };
+            let highs: Vec<f32> = config
+                .energy_bands
+                .iter()
+                .zip(&metrics.band_energies)
+                .filter(|(band, _)| band.min_freq >= HIGH_BAND_MIN_FREQ)
+                .map(|(_, energy)| energy.energy)
+                .collect();
+            let high_energy = highs.iter().sum::<f32>() / highs.len().max(1) as f32;
+            (
+                settings,
+                config.threshold_mode,
+                config.phrase_length,
+                config.buildup_window_seconds as f64,
+                high_energy,
+            )
+        };
+
+        // Beats are accented by bass, so loud bass-less risers don't read as
+        // phrase starts
+        let bar_position = match metrics.beat {
+            Some(_) => self
+                .bar_tracker
+                .on_beat(metrics.bass_energy, phrase_length.bars()),
+            None => self.bar_tracker.position(),
+        };
+
+        // Adaptive thresholds compare ranks in the recent history. Until the
+        // analyzer has heard enough to rank against, absolute levels are used
+        let relative = &metrics.relative;
+        let adaptive = threshold_mode == ThresholdMode::Adaptive && relative.calibrated;
+        let (bass_level, loudness_level, bass_ceiling) = if adaptive {
+            (
+                relative.bass_energy,
+                relative.loudness,
+                drop_settings.enter_threshold * relative.bass_reference,
+            )
+        } else {
+            (
+                metrics.bass_energy,
+                metrics.loudness,
+                drop_settings.enter_threshold,
+            )
+        };
+
+        let time = metrics.time;
+        let cues = BuildupCues {
+            spectral_centroid: metrics.spectral_centroid,
+            high_energy,
+            onsets: metrics.onsets.iter().filter(|o| o.band.is_none()).count(),
+            bass_energy: metrics.bass_energy,
+        };
+        let buildup_intensity =
+            self.buildup_detector
+                .update(time, cues, buildup_window, bass_ceiling);
+
+        let mut events = Vec::new();
+        match self.drop_detector.update(
+            time,
+            bass_level,
+            loudness_level,
+            buildup_intensity,
+            drop_settings,
+        ) {
+            Some(DropState::Drop) => events.push(ControllerEvent::DropStarted { time }),
+            Some(DropState::Release) => events.push(ControllerEvent::DropEnded { time }),
+            _ => {}
+        }
+        events.extend(metrics.beat.map(|beat| ControllerEvent::Beat {
+            time: beat.time,
+            bpm: beat.bpm,
+            downbeat: bar_position.beat_in_bar == 1,
+        }));
+        events.extend(metrics.drum_hits.iter().map(|hit| ControllerEvent::Hit {
+            time: hit.time,
+            drum: hit.drum,
+            velocity: hit.velocity,
+        }));
+        events.extend(metrics.onsets.iter().filter_map(|onset| {
+            let band = match &onset.band {
+                Some(name) => Some(
+                    metrics
+                        .band_energies
+                        .iter()
+                        .position(|band| &band.name == name)?,
+                ),
+                None => None,
+            };
+            Some(ControllerEvent::Onset {
+                time: onset.time,
+                band,
+            })
+        }));
+
+        let drop_state = self.drop_detector.state();
+        let time_to_drop = (buildup_intensity >= BUILDUP_PREDICTION_THRESHOLD
+            && !matches!(drop_state, DropState::Drop | DropState::Sustain))
+        .then(|| time_to_phrase_start(metrics.tempo, bar_position, phrase_length.bars()))
+        .flatten();
 
         ControllerOutput {
-            is_drop,
+            drop_state,
+            events,
             loudness: metrics.loudness,
+            stereo_width: metrics.stereo_width,
+            envelopes: metrics.envelopes,
+            spectrum: metrics.spectrum.normalized,
+            band_energies: metrics.band_energies,
+            tempo: metrics.tempo,
+            bar_position,
+            pitch: metrics.pitch.voiced.then_some(metrics.pitch),
+            key: metrics.key,
+            brightness: brightness(metrics.spectral_centroid),
+            noisiness: (metrics.spectral_flatness / NOISE_FLATNESS).min(1.0),
+            buildup_intensity,
+            time_to_drop,
         }
     }
 }
+
+/// Position of `centroid` between the dark and bright reference centroids,
+/// in octaves. Silence reads as dark.
+fn brightness(centroid: f32) -> f32 {
+    if centroid <= 0.0 {
+        return 0.0;
+    }
+    ((centroid / DARK_CENTROID_HZ).log2() / (BRIGHT_CENTROID_HZ / DARK_CENTROID_HZ).log2())
+        .clamp(0.0, 1.0)
+}
+
+/// Seconds until the first beat of the next phrase, if the tempo and the
+/// phrase position are known.
+fn time_to_phrase_start(tempo: Tempo, position: BarPosition, phrase_bars: usize) -> Option<f32> {
+    if tempo.bpm <= 0.0 || position.bar_in_phrase == 0 {
+        return None;
+    }
+    let bars_left = phrase_bars.saturating_sub(position.bar_in_phrase);
+    let beats_left = bars_left * BEATS_PER_BAR + (BEATS_PER_BAR - position.beat_in_bar);
+    Some((beats_left as f32 + 1.0 - tempo.phase) * 60.0 / tempo.bpm)
+}
@@ -0,0 +1,95 @@
+use std::collections::VecDeque;
+
+/// Beats per bar. Dance music is almost always in 4/4.
+pub const BEATS_PER_BAR: usize = 4;
+
+/// How much accent evidence is kept per new beat or bar, so the grid can
+/// re-align after a mix into a track with a different downbeat.
+const ACCENT_MEMORY: f32 = 0.995;
+
+/// Where the current beat sits in its bar and phrase.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct BarPosition {
+    /// Beat within the bar, 1-4, 0 before the first beat.
+    pub beat_in_bar: usize,
+    /// Bar within the phrase, 1 to the phrase length, 0 before the first downbeat.
+    pub bar_in_phrase: usize,
+}
+
+/// Counts beats into bars and phrases, placing the downbeat and the phrase
+/// start where the music accents.
+///
+/// A beat's accent is how far its level rises above the beats just before it:
+/// the kick after a fill, the bass coming back after a break. Accents are
+/// accumulated per position in the bar, and the position that collects the
+/// most is taken as the downbeat. Downbeat accents are accumulated the same
+/// way per bar of the phrase, which puts phrase starts on the drops.
+pub struct BarTracker {
+    beats: usize,
+    bars: usize,
+    recent_levels: VecDeque<f32>,
+    beat_accents: Vec<f32>,
+    bar_accents: Vec<f32>,
+    position: BarPosition,
+}
+
+impl BarTracker {
+    pub fn new() -> Self {
+        Self {
+            beats: 0,
+            bars: 0,
+            recent_levels: VecDeque::with_capacity(BEATS_PER_BAR),
+            beat_accents: vec![0.0; BEATS_PER_BAR],
+            bar_accents: Vec::new(),
+            position: BarPosition::default(),
+        }
+    }
+
+    pub fn position(&self) -> BarPosition {
+        self.position
+    }
+
+    /// Advances by one beat whose level (0-1) is `level`.
+    pub fn on_beat(&mut self, level: f32, phrase_bars: usize) -> BarPosition {
+        let accent = if self.recent_levels.is_empty() {
+            0.0
+        } else {
+            let mean = self.recent_levels.iter().sum::<f32>() / self.recent_levels.len() as f32;
+            (level - mean).max(0.0)
+        };
+        if self.recent_levels.len() == BEATS_PER_BAR {
+            self.recent_levels.pop_front();
+        }
+        self.recent_levels.push_back(level);
+
+        let slot = self.beats % BEATS_PER_BAR;
+        let downbeat = accumulate(&mut self.beat_accents, slot, accent);
+        let beat_in_bar = (slot + BEATS_PER_BAR - downbeat) % BEATS_PER_BAR + 1;
+        self.beats += 1;
+
+        let phrase_bars = phrase_bars.max(1);
+        if self.bar_accents.len() != phrase_bars {
+            self.bar_accents = vec![0.0; phrase_bars];
+        }
+        if beat_in_bar == 1 {
+            let slot = self.bars % phrase_bars;
+            let phrase_start = accumulate(&mut self.bar_accents, slot, accent);
+            self.position.bar_in_phrase = (slot + phrase_bars - phrase_start) % phrase_bars + 1;
+            self.bars += 1;
+        }
+        self.position.beat_in_bar = beat_in_bar;
+
+        self.position
+    }
+}
+
+/// Decays every slot, adds `accent` to `slot` and returns the strongest slot.
+fn accumulate(accents: &mut [f32], slot: usize, accent: f32) -> usize {
+    for value in accents.iter_mut() {
+        *value *= ACCENT_MEMORY;
+    }
+    accents[slot] += accent;
+    (0..accents.len())
+        .max_by(|&a, &b| accents[a].total_cmp(&accents[b]).then(b.cmp(&a)))
+        .unwrap_or(0)
+}
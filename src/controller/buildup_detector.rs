@@ -0,0 +1,155 @@
+use std::collections::VecDeque;
+
+/// Window over which onsets are counted into a density, in seconds.
+const DENSITY_SECONDS: f64 = 1.0;
+
+/// Trend slopes that count as a full-strength build-up cue.
+/// Spectral centroid, in octaves per second.
+const CENTROID_RISE: f32 = 0.08;
+/// High-band energy, in energy per second.
+const HIGH_RISE: f32 = 0.03;
+/// Onset density, in onsets per second per second.
+const DENSITY_RISE: f32 = 1.0;
+
+/// Share of the bass ceiling above which the low end counts as present.
+const BASS_PRESENT: f32 = 0.5;
+
+/// How long the low end must have been gone before trends are measured, so
+/// the analysis window has moved past the bass cutting out and the onset
+/// density only counts onsets since.
+const SETTLE_SECONDS: f64 = DENSITY_SECONDS;
+
+/// Time constant of the output smoothing, in seconds.
+const SMOOTHING_SECONDS: f64 = 0.5;
+
+/// One frame of the cues a build-up is recognized by.
+#[derive(Clone, Copy, Debug)]
+pub struct BuildupCues {
+    pub spectral_centroid: f32,
+    /// Energy of the bands above the bass and mids, 0-1.
+    pub high_energy: f32,
+    /// Full-spectrum onsets detected in this frame.
+    pub onsets: usize,
+    pub bass_energy: f32,
+}
+
+struct TrendSample {
+    time: f64,
+    centroid_octaves: f32,
+    high_energy: f32,
+    onset_density: f32,
+}
+
+/// Scores how much the music sounds like a pre-drop build-up.
+///
+/// Build-ups raise the spectral centroid and the high-frequency energy,
+/// speed up snare rolls and filter the low end away. Each rising cue is
+/// measured as a least-squares slope over a few seconds, and the bass
+/// energy decides how much of the combined score counts.
+///
+/// Trends are only measured once the low end has been gone for a moment
+/// and while there is sound, so the jump in brightness when the bass of a
+/// drop cuts out doesn't read as a build-up.
+pub struct BuildupDetector {
+    history: VecDeque<TrendSample>,
+    onset_times: VecDeque<f64>,
+    /// When the low end was last present, or the stream silent or started.
+    interrupted: f64,
+    intensity: f32,
+    last_time: Option<f64>,
+}
+
+impl BuildupDetector {
+    pub fn new() -> Self {
+        Self {
+            history: VecDeque::new(),
+            onset_times: VecDeque::new(),
+            interrupted: 0.0,
+            intensity: 0.0,
+            last_time: None,
+        }
+    }
+
+    /// Adds one frame and returns the smoothed build-up intensity, 0-1.
+    /// `window_seconds` is how far back trends are measured, and bass energy
+    /// at `bass_ceiling` or above rules a build-up out.
+    pub fn update(
+        &mut self,
+        time: f64,
+        cues: BuildupCues,
+        window_seconds: f64,
+        bass_ceiling: f32,
+    ) -> f32 {
+        for _ in 0..cues.onsets {
+            self.onset_times.push_back(time);
+        }
+        while self
+            .onset_times
+            .front()
+            .is_some_and(|&t| time - t > DENSITY_SECONDS)
+        {
+            self.onset_times.pop_front();
+        }
+
+        // Bass, silence or the stream starting breaks the trend
+        if self.last_time.is_none()
+            || cues.bass_energy >= bass_ceiling * BASS_PRESENT
+            || cues.spectral_centroid <= 0.0
+        {
+            self.history.clear();
+            self.interrupted = time;
+        } else if time - self.interrupted >= SETTLE_SECONDS {
+            self.history.push_back(TrendSample {
+                time,
+                centroid_octaves: cues.spectral_centroid.log2(),
+                high_energy: cues.high_energy,
+                onset_density: self.onset_times.len() as f32 / DENSITY_SECONDS as f32,
+            });
+        }
+        while self
+            .history
+            .front()
+            .is_some_and(|s| time - s.time > window_seconds)
+        {
+            self.history.pop_front();
+        }
+
+        let history = self.history.make_contiguous();
+        let rising = |value: fn(&TrendSample) -> f32, full: f32| {
+            (slope(history, value) / full).clamp(0.0, 1.0)
+        };
+        let centroid = rising(|s| s.centroid_octaves, CENTROID_RISE);
+        let high = rising(|s| s.high_energy, HIGH_RISE);
+        let density = rising(|s| s.onset_density, DENSITY_RISE);
+        let bass_absent = (1.0 - cues.bass_energy / bass_ceiling.max(f32::EPSILON)).clamp(0.0, 1.0);
+        let raw = bass_absent * (0.4 * density + 0.3 * centroid + 0.3 * high);
+
+        let dt = self.last_time.map_or(0.0, |last| (time - last).max(0.0));
+        let alpha = 1.0 - (-dt / SMOOTHING_SECONDS).exp() as f32;
+        self.intensity += (raw - self.intensity) * alpha;
+        self.last_time = Some(time);
+
+        self.intensity
+    }
+}
+
+/// Least-squares slope per second of one cue over `samples`.
+fn slope(samples: &[TrendSample], value: fn(&TrendSample) -> f32) -> f32 {
+    let n = samples.len() as f64;
+    if n < 2.0 {
+        return 0.0;
+    }
+    let mean_t = samples.iter().map(|s| s.time).sum::<f64>() / n;
+    let mean_v = samples.iter().map(|s| value(s) as f64).sum::<f64>() / n;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for sample in samples {
+        let dt = sample.time - mean_t;
+        covariance += dt * (value(sample) as f64 - mean_v);
+        variance += dt * dt;
+    }
+    if variance > 0.0 {
+        (covariance / variance) as f32
+    } else {
+        0.0
+    }
+}
@@ -0,0 +1,113 @@
+/// Phase of the drop cycle.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum DropState {
+    /// Nothing going on.
+    #[default]
+    Idle,
+    /// Loud or building up, but without the bass of a drop yet.
+    BuildUp,
+    /// The drop just hit. Lasts at least the configured hold time.
+    Drop,
+    /// Drop continuing after the hold time, until bass or loudness fall away.
+    Sustain,
+    /// Drop ended, no new drop until the cooldown has passed.
+    Release,
+}
+
+impl DropState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DropState::Idle => "Idle",
+            DropState::BuildUp => "Build-up",
+            DropState::Drop => "Drop",
+            DropState::Sustain => "Sustain",
+            DropState::Release => "Release",
+        }
+    }
+}
+
+/// Thresholds and timings of the drop state machine, in config units.
+#[derive(Clone, Copy, Debug)]
+pub struct DropSettings {
+    /// Bass energy that starts a drop.
+    pub enter_threshold: f32,
+    /// Bass energy below which a drop starts ending.
+    pub exit_threshold: f32,
+    /// Loudness needed for a build-up or a drop.
+    pub loudness_gate: f32,
+    /// Build-up intensity that counts as a build-up even below the loudness gate.
+    pub buildup_threshold: f32,
+    /// Minimum drop length in seconds.
+    pub hold_seconds: f64,
+    /// How long bass or loudness must stay low before a drop ends, in seconds.
+    pub release_seconds: f64,
+    /// Time after a drop ends before the next one can start, in seconds.
+    pub cooldown_seconds: f64,
+}
+
+/// Drop state machine with hysteresis, hold time and cooldown.
+pub struct DropDetector {
+    state: DropState,
+    /// When the current state was entered.
+    entered: f64,
+    /// When levels dropped below the exit thresholds during a sustain.
+    fading_since: Option<f64>,
+}
+
+impl DropDetector {
+    pub fn new() -> Self {
+        Self {
+            state: DropState::Idle,
+            entered: 0.0,
+            fading_since: None,
+        }
+    }
+
+    /// Advances the state machine to `time`. Returns the new state if it changed.
+    pub fn update(
+        &mut self,
+        time: f64,
+        bass_energy: f32,
+        loudness: f32,
+        buildup_intensity: f32,
+        settings: DropSettings,
+    ) -> Option<DropState> {
+        let loud = loudness >= settings.loudness_gate;
+        let hot = loud && bass_energy >= settings.enter_threshold;
+        let building = loud || buildup_intensity >= settings.buildup_threshold;
+        let in_state = time - self.entered;
+
+        let next = match self.state {
+            DropState::Idle | DropState::BuildUp if hot => DropState::Drop,
+            DropState::Idle if building => DropState::BuildUp,
+            DropState::BuildUp if !building => DropState::Idle,
+            DropState::Drop if in_state >= settings.hold_seconds => DropState::Sustain,
+            DropState::Sustain => {
+                let fading = !loud || bass_energy < settings.exit_threshold;
+                if !fading {
+                    self.fading_since = None;
+                    DropState::Sustain
+                } else if time - *self.fading_since.get_or_insert(time) >= settings.release_seconds
+                {
+                    DropState::Release
+                } else {
+                    DropState::Sustain
+                }
+            }
+            DropState::Release if in_state >= settings.cooldown_seconds => DropState::Idle,
+            state => state,
+        };
+
+        if next == self.state {
+            return None;
+        }
+        self.state = next;
+        self.entered = time;
+        self.fading_since = None;
+        Some(next)
+    }
+
+    pub fn state(&self) -> DropState {
+        self.state
+    }
+}
@@ -1,34 +1,55 @@
-use crate::audio::{AudioAnalyzer, AudioMetrics, AudioStream, audio_stream};
+use crate::audio::{
+    AudioAnalyzer, AudioMetrics, AudioSource, FileSource, GeneratorSource, SignalSettings,
+    SourceKind, audio_stream,
+};
 use crate::config::{APP_VERSION, AudioConfig};
-use crate::controller::ControllerOutput;
+use crate::controller::{ControllerEvent, ControllerOutput};
 use crate::visual::VisualEngine;
 use cpal::traits::{DeviceTrait, HostTrait};
 use eframe::egui;
-use log::{debug, info};
-use std::sync::{Arc, Mutex, RwLock};
-
-use super::components::{render_config_panel, render_live_monitoring, render_waveform};
+use log::{debug, info, warn};
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock, mpsc};
+
+use super::components::{
+    render_config_panel, render_goniometer, render_live_monitoring, render_signal_settings,
+    render_spectrum, render_waveform,
+};
+
+/// Which input feeds the analyzer, as chosen in the top panel.
+#[derive(Clone, PartialEq, Debug, Default)]
+struct InputSelection {
+    kind: SourceKind,
+    device_idx: usize,
+    file_path: String,
+    file_loop: bool,
+    signal: SignalSettings,
+}
 
 pub struct AppState {
     active_config: Arc<RwLock<AudioConfig>>,
     pending_config: AudioConfig, // Local copy for sliders
     devices: Vec<String>,
-    active_device_idx: usize,
-    pending_device_idx: usize, // Local selection for device selector
+    active_input: InputSelection,
+    pending_input: InputSelection, // Local selection for input selector
     analyzer: Arc<Mutex<AudioAnalyzer>>,
-    audio_stream: Option<AudioStream>,
+    audio_source: Option<Box<dyn AudioSource>>,
     analyzer_metrics: Arc<RwLock<AudioMetrics>>,
     controller_output: Arc<RwLock<ControllerOutput>>,
+    controller_events: mpsc::Receiver<ControllerEvent>,
     visuals_window_open: bool,
     visuals_window: VisualEngine,
 }
 
 impl eframe::App for AppState {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Everything the controller raised since the last repaint
+        let events: Vec<ControllerEvent> = self.controller_events.try_iter().collect();
+
         self.render_top_panel(ctx);
         self.render_bottom_panel(ctx);
-        self.render_central_panel(ctx);
-        self.render_visualizer_window(ctx);
+        self.render_central_panel(ctx, &events);
+        self.render_visualizer_window(ctx, &events);
 
         ctx.request_repaint();
     }
@@ -40,6 +61,7 @@ impl AppState {
         analyzer: Arc<Mutex<AudioAnalyzer>>,
         analyzer_metrics: Arc<RwLock<AudioMetrics>>,
         controller_output: Arc<RwLock<ControllerOutput>>,
+        controller_events: mpsc::Receiver<ControllerEvent>,
     ) -> Self {
         debug!("Initializing GUI state...");
         let host = cpal::default_host();
@@ -69,15 +91,17 @@ impl AppState {
             .unwrap_or("None");
         info!("Selected initial audio device: {selected_device}");
 
-        let audio_stream =
-            audio_stream::create_audio_stream(selected_device_idx, &devices, analyzer.clone());
+        let input = InputSelection {
+            device_idx: selected_device_idx,
+            file_loop: true,
+            ..Default::default()
+        };
+        let audio_source = open_source(&input, &devices, analyzer.clone());
 
         let pending_config = config.read().unwrap().clone();
         debug!(
-            "Initial config loaded: sample_rate={}, buffer_size={}, update_interval={}ms",
-            pending_config.sample_rate,
-            pending_config.buffer_size,
-            pending_config.update_interval_ms
+            "Initial config loaded: buffer_size={}, hop_size={}, channel_mode={:?}",
+            pending_config.buffer_size, pending_config.hop_size, pending_config.channel_mode
         );
 
         let visuals_window = VisualEngine::new(controller_output.clone());
@@ -86,27 +110,22 @@ impl AppState {
             active_config: config,
             pending_config,
             devices,
-            pending_device_idx: selected_device_idx,
-            active_device_idx: selected_device_idx,
+            pending_input: input.clone(),
+            active_input: input,
             analyzer,
-            audio_stream,
+            audio_source,
             analyzer_metrics,
             controller_output,
+            controller_events,
             visuals_window_open: false,
             visuals_window,
         }
     }
 
     fn apply_settings(&mut self) {
-        let device_name = self
-            .devices
-            .get(self.pending_device_idx)
-            .map(|s| s.as_str())
-            .unwrap_or("Unknown");
-
         debug!(
-            "Applying settings - Device: {}, Config: {:?}",
-            device_name, self.pending_config
+            "Applying settings - Input: {:?}, Config: {:?}",
+            self.pending_input, self.pending_config
         );
 
         // Lock and copy pending config to shared config
@@ -115,14 +134,13 @@ impl AppState {
             *config = self.pending_config.clone();
         }
 
-        debug!("Reloading audio stream with new device...");
-        self.audio_stream = audio_stream::create_audio_stream(
-            self.pending_device_idx,
-            &self.devices,
-            self.analyzer.clone(),
-        );
+        debug!("Reloading audio source...");
+        // Drop the old source first so it can't push samples in its own format
+        // after the analyzer has switched to the new one.
+        self.audio_source = None;
+        self.audio_source = open_source(&self.pending_input, &self.devices, self.analyzer.clone());
 
-        self.active_device_idx = self.pending_device_idx;
+        self.active_input = self.pending_input.clone();
         info!("Settings applied successfully");
     }
 
@@ -134,9 +152,9 @@ impl AppState {
 
     fn disable_apply_button(&self) -> bool {
         let config_unchanged = self.pending_config == *self.active_config.read().unwrap();
-        let device_unchanged = self.pending_device_idx == self.active_device_idx;
+        let input_unchanged = self.pending_input == self.active_input;
 
-        config_unchanged && device_unchanged
+        config_unchanged && input_unchanged
     }
 
     fn render_top_panel(&mut self, ctx: &egui::Context) {
@@ -145,10 +163,30 @@ impl AppState {
             ui.horizontal(|ui| {
                 ui.heading(format!("EDEN {APP_VERSION}"));
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    if self.audio_stream.is_some() {
-                        ui.colored_label(egui::Color32::GREEN, "Listening");
+                    if let Some(source) = &self.audio_source {
+                        let (sample_rate, channels, overruns) = {
+                            let analyzer = self.analyzer.lock().unwrap();
+                            (
+                                analyzer.sample_rate(),
+                                analyzer.channels(),
+                                analyzer.overrun_count(),
+                            )
+                        };
+                        let color = if source.is_running() {
+                            egui::Color32::GREEN
+                        } else {
+                            egui::Color32::GRAY
+                        };
+                        ui.colored_label(
+                            color,
+                            format!("{} ({sample_rate} Hz, {channels} ch)", source.label()),
+                        );
+                        if overruns > 0 {
+                            ui.colored_label(egui::Color32::YELLOW, format!("{overruns} dropped"))
+                                .on_hover_text("Frames dropped because analysis fell behind");
+                        }
                     } else {
-                        ui.colored_label(egui::Color32::RED, "No Audio Stream");
+                        ui.colored_label(egui::Color32::RED, "No Audio Source");
                     }
                 });
             });
@@ -156,23 +194,54 @@ impl AppState {
             ui.separator();
             ui.add_space(4.0);
 
-            // Device Selection
+            // Input Selection
             ui.horizontal(|ui| {
-                ui.label("Audio Device:");
-                egui::ComboBox::from_id_salt("device_selector")
-                    .selected_text(
-                        self.devices
-                            .get(self.pending_device_idx)
-                            .map(|name| name.as_str())
-                            .unwrap_or("No devices"),
-                    )
+                ui.label("Input:");
+                egui::ComboBox::from_id_salt("source_selector")
+                    .selected_text(self.pending_input.kind.label())
                     .show_ui(ui, |ui| {
-                        for (idx, name) in self.devices.iter().enumerate() {
-                            ui.selectable_value(&mut self.pending_device_idx, idx, name);
+                        for kind in SourceKind::ALL {
+                            ui.selectable_value(&mut self.pending_input.kind, kind, kind.label());
                         }
                     });
             });
             ui.add_space(4.0);
+
+            match self.pending_input.kind {
+                SourceKind::Device => {
+                    ui.horizontal(|ui| {
+                        ui.label("Audio Device:");
+                        egui::ComboBox::from_id_salt("device_selector")
+                            .selected_text(
+                                self.devices
+                                    .get(self.pending_input.device_idx)
+                                    .map(|name| name.as_str())
+                                    .unwrap_or("No devices"),
+                            )
+                            .show_ui(ui, |ui| {
+                                for (idx, name) in self.devices.iter().enumerate() {
+                                    ui.selectable_value(
+                                        &mut self.pending_input.device_idx,
+                                        idx,
+                                        name,
+                                    );
+                                }
+                            });
+                    });
+                }
+                SourceKind::File => {
+                    ui.horizontal(|ui| {
+                        ui.label("File Path:")
+                            .on_hover_text("WAV, FLAC, MP3 or OGG file");
+                        ui.text_edit_singleline(&mut self.pending_input.file_path);
+                        ui.checkbox(&mut self.pending_input.file_loop, "Loop");
+                    });
+                }
+                SourceKind::Generator => {
+                    render_signal_settings(ui, &mut self.pending_input.signal);
+                }
+            }
+            ui.add_space(4.0);
         });
     }
 
@@ -213,7 +282,7 @@ impl AppState {
         });
     }
 
-    fn render_central_panel(&mut self, ctx: &egui::Context) {
+    fn render_central_panel(&mut self, ctx: &egui::Context, events: &[ControllerEvent]) {
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::ScrollArea::vertical()
                 .auto_shrink([false; 2])
@@ -223,11 +292,23 @@ impl AppState {
                     // Live Monitoring Section
                     let analyzer_metrics = self.analyzer_metrics.read().unwrap().clone();
                     let controller_output = self.controller_output.read().unwrap().clone();
-                    render_live_monitoring(ui, &analyzer_metrics, &controller_output);
+                    render_live_monitoring(ui, &analyzer_metrics, &controller_output, events);
+
+                    // Waveform and Stereo Field Visualization
+                    let (audio_buffer, (left, right)) = {
+                        let analyzer = self.analyzer.lock().unwrap();
+                        (analyzer.get_buffer(), analyzer.get_stereo_buffers())
+                    };
+                    ui.horizontal_top(|ui| {
+                        let waveform_width = ui.available_width() - 150.0;
+                        ui.allocate_ui(egui::vec2(waveform_width, 0.0), |ui| {
+                            render_waveform(ui, &audio_buffer);
+                        });
+                        render_goniometer(ui, &left, &right, analyzer_metrics.phase_correlation);
+                    });
 
-                    // Waveform Visualization
-                    let audio_buffer = self.analyzer.lock().unwrap().get_buffer();
-                    render_waveform(ui, &audio_buffer);
+                    // Spectrum Visualization
+                    render_spectrum(ui, &analyzer_metrics.spectrum);
 
                     ui.add_space(20.0);
 
@@ -237,7 +318,7 @@ impl AppState {
         });
     }
 
-    fn render_visualizer_window(&mut self, ctx: &egui::Context) {
+    fn render_visualizer_window(&mut self, ctx: &egui::Context, events: &[ControllerEvent]) {
         if self.visuals_window_open {
             let visualizer_id = egui::ViewportId::from_hash_of("edenfx_visualizer");
 
@@ -251,9 +332,35 @@ impl AppState {
                     if ctx.input(|i| i.viewport().close_requested()) {
                         self.visuals_window_open = false;
                     }
-                    self.visuals_window.render(ctx);
+                    self.visuals_window.render(ctx, events);
                 },
             );
         }
     }
 }
+
+fn open_source(
+    input: &InputSelection,
+    devices: &[String],
+    analyzer: Arc<Mutex<AudioAnalyzer>>,
+) -> Option<Box<dyn AudioSource>> {
+    match input.kind {
+        SourceKind::Device => {
+            audio_stream::create_audio_stream(input.device_idx, devices, analyzer)
+                .map(|stream| Box::new(stream) as Box<dyn AudioSource>)
+        }
+        SourceKind::File => {
+            match FileSource::open(Path::new(&input.file_path), input.file_loop, analyzer) {
+                Ok(source) => Some(Box::new(source)),
+                Err(e) => {
+                    warn!("Failed to open audio file {}: {e}", input.file_path);
+                    None
+                }
+            }
+        }
+        SourceKind::Generator => Some(Box::new(GeneratorSource::new(
+            input.signal.clone(),
+            analyzer,
+        ))),
+    }
+}
@@ -1,7 +1,13 @@
 mod config_panel;
+mod goniometer;
 mod live_monitoring;
+mod signal_panel;
+mod spectrum;
 mod waveform;
 
 pub use config_panel::render_config_panel;
+pub use goniometer::render_goniometer;
 pub use live_monitoring::render_live_monitoring;
+pub use signal_panel::render_signal_settings;
+pub use spectrum::render_spectrum;
 pub use waveform::render_waveform;
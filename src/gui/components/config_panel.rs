@@ -1,13 +1,51 @@
 use eframe::egui;
 
-use crate::config::AudioConfig;
+use crate::config::{
+    AudioConfig, ChannelMode, EnergyBand, EnvelopeSettings, FilterKind, FilterStage,
+    FrequencyScale, PhraseLength, ThresholdMode, WindowFunction,
+};
 
 pub fn render_config_panel(ui: &mut egui::Ui, config: &mut AudioConfig) {
     ui.label(egui::RichText::new("Configuration").size(16.0));
     ui.add_space(8.0);
 
-    // Bass Detection Settings
-    render_bass_detection(ui, config);
+    // Drop Detection Settings
+    render_drop_detection(ui, config);
+
+    ui.add_space(8.0);
+
+    // Energy Band Settings
+    render_energy_bands(ui, config);
+
+    ui.add_space(8.0);
+
+    // Envelope Settings
+    render_envelopes(ui, config);
+
+    ui.add_space(8.0);
+
+    // Onset, Tempo and Key Settings
+    render_onsets_and_tempo(ui, config);
+
+    ui.add_space(8.0);
+
+    // Source Separation Settings
+    render_source_separation(ui, config);
+
+    ui.add_space(8.0);
+
+    // Pitch Tracking Settings
+    render_pitch_tracking(ui, config);
+
+    ui.add_space(8.0);
+
+    // Spectrum Settings
+    render_spectrum_settings(ui, config);
+
+    ui.add_space(8.0);
+
+    // Pre-Filter Settings
+    render_pre_filters(ui, config);
 
     ui.add_space(8.0);
 
@@ -17,29 +55,36 @@ pub fn render_config_panel(ui: &mut egui::Ui, config: &mut AudioConfig) {
     ui.add_space(20.0);
 }
 
-fn render_bass_detection(ui: &mut egui::Ui, config: &mut AudioConfig) {
-    egui::CollapsingHeader::new("Bass Detection")
+fn render_drop_detection(ui: &mut egui::Ui, config: &mut AudioConfig) {
+    egui::CollapsingHeader::new("Drop Detection")
         .default_open(true)
         .show(ui, |ui| {
             ui.add_space(4.0);
-            egui::Grid::new("bass_settings_grid")
+            egui::Grid::new("drop_settings_grid")
                 .num_columns(2)
                 .spacing([20.0, 8.0])
                 .show(ui, |ui| {
-                    ui.label("Max Bass Freq:")
-                        .on_hover_text("What counts as 'bass' - lower = only deep bass");
-                    ui.add(
-                        egui::Slider::new(&mut config.bass_freq_max, 20.0..=500.0).suffix(" Hz"),
+                    ui.label("Thresholds:").on_hover_text(
+                        "Absolute levels, or ranks in the recent history (0.9 = top 10%)",
                     );
+                    egui::ComboBox::from_id_salt("threshold_mode_selector")
+                        .selected_text(config.threshold_mode.label())
+                        .show_ui(ui, |ui| {
+                            for mode in ThresholdMode::ALL {
+                                ui.selectable_value(&mut config.threshold_mode, mode, mode.label());
+                            }
+                        });
                     ui.end_row();
 
-                    ui.label("Bass Sensitivity:")
-                        .on_hover_text("Higher = more sensitive to bass");
-                    ui.add(egui::Slider::new(
-                        &mut config.bass_energy_multiplier,
-                        1.0..=5.0,
-                    ));
-                    ui.end_row();
+                    if config.threshold_mode == ThresholdMode::Adaptive {
+                        ui.label("Adaptive Window:")
+                            .on_hover_text("Length of the history thresholds are ranked against");
+                        ui.add(
+                            egui::Slider::new(&mut config.adaptive_window_seconds, 10.0..=300.0)
+                                .suffix(" s"),
+                        );
+                        ui.end_row();
+                    }
 
                     ui.label("Drop Threshold:")
                         .on_hover_text("When to trigger DROP detection");
@@ -48,10 +93,507 @@ fn render_bass_detection(ui: &mut egui::Ui, config: &mut AudioConfig) {
                         0.0..=1.0,
                     ));
                     ui.end_row();
+
+                    ui.label("Exit Threshold:")
+                        .on_hover_text("Bass level below which a drop starts to end");
+                    ui.add(egui::Slider::new(
+                        &mut config.drop_exit_threshold,
+                        0.0..=config.drop_detection_threshold,
+                    ));
+                    ui.end_row();
+
+                    ui.label("Loudness Gate:")
+                        .on_hover_text("Loudness needed for a build-up or a drop");
+                    ui.add(egui::Slider::new(&mut config.drop_loudness_gate, 0.0..=1.0));
+                    ui.end_row();
+
+                    ui.label("Hold:").on_hover_text("Minimum length of a drop");
+                    ui.add(
+                        egui::Slider::new(&mut config.drop_hold_seconds, 0.0..=16.0).suffix(" s"),
+                    );
+                    ui.end_row();
+
+                    ui.label("Release:")
+                        .on_hover_text("How long levels must stay low before a drop ends");
+                    ui.add(
+                        egui::Slider::new(&mut config.drop_release_seconds, 0.0..=4.0).suffix(" s"),
+                    );
+                    ui.end_row();
+
+                    ui.label("Cooldown:")
+                        .on_hover_text("Time after a drop before the next one can start");
+                    ui.add(
+                        egui::Slider::new(&mut config.drop_cooldown_seconds, 0.0..=30.0)
+                            .suffix(" s"),
+                    );
+                    ui.end_row();
+
+                    ui.label("Build-up Window:").on_hover_text(
+                        "How far back rising highs and snare rolls are measured to spot a build-up",
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut config.buildup_window_seconds, 1.0..=16.0)
+                            .suffix(" s"),
+                    );
+                    ui.end_row();
+                });
+        });
+}
+
+fn render_energy_bands(ui: &mut egui::Ui, config: &mut AudioConfig) {
+    egui::CollapsingHeader::new("Energy Bands")
+        .default_open(false)
+        .show(ui, |ui| {
+            ui.add_space(4.0);
+            let mut removed = None;
+            let mut renamed = None;
+            egui::Grid::new("energy_bands_grid")
+                .num_columns(7)
+                .spacing([8.0, 8.0])
+                .show(ui, |ui| {
+                    ui.label("Name");
+                    ui.label("From");
+                    ui.label("To");
+                    ui.label("Gain")
+                        .on_hover_text("Higher = more sensitive to this band");
+                    ui.label("Smoothing")
+                        .on_hover_text("Higher = steadier but slower readings");
+                    ui.label("Bass")
+                        .on_hover_text("Counts towards bass energy and drop detection");
+                    ui.end_row();
+
+                    for (idx, band) in config.energy_bands.iter_mut().enumerate() {
+                        let previous_name = band.name.clone();
+                        if ui
+                            .add(egui::TextEdit::singleline(&mut band.name).desired_width(70.0))
+                            .on_hover_text("Must be unique")
+                            .changed()
+                        {
+                            renamed = Some((idx, previous_name));
+                        }
+                        ui.add(
+                            egui::DragValue::new(&mut band.min_freq)
+                                .range(1.0..=band.max_freq)
+                                .suffix(" Hz"),
+                        );
+                        ui.add(
+                            egui::DragValue::new(&mut band.max_freq)
+                                .range(band.min_freq..=24000.0)
+                                .suffix(" Hz"),
+                        );
+                        ui.add(
+                            egui::DragValue::new(&mut band.multiplier)
+                                .range(0.1..=10.0)
+                                .speed(0.05),
+                        );
+                        ui.add(
+                            egui::DragValue::new(&mut band.smoothing)
+                                .range(0.0..=0.99)
+                                .speed(0.01),
+                        );
+                        ui.checkbox(&mut band.bass, "");
+                        if ui.small_button("✖").on_hover_text("Remove band").clicked() {
+                            removed = Some(idx);
+                        }
+                        ui.end_row();
+                    }
+                });
+
+            // Bands are told apart by name, so a clashing rename is undone
+            if let Some((idx, previous_name)) = renamed
+                && band_name_taken(&config.energy_bands, &config.energy_bands[idx].name, idx)
+            {
+                config.energy_bands[idx].name = previous_name;
+            }
+            if let Some(idx) = removed {
+                config.energy_bands.remove(idx);
+            }
+
+            ui.add_space(4.0);
+            if ui.button("+ Add Band").clicked() {
+                let name = (1..)
+                    .map(|n| format!("band {n}"))
+                    .find(|name| !band_name_taken(&config.energy_bands, name, usize::MAX))
+                    .unwrap();
+                config
+                    .energy_bands
+                    .push(EnergyBand::new(&name, 100.0, 1000.0, 0.3, false));
+            }
+        });
+}
+
+/// Whether a band other than the one at `idx` is called `name`.
+fn band_name_taken(bands: &[EnergyBand], name: &str, idx: usize) -> bool {
+    bands
+        .iter()
+        .enumerate()
+        .any(|(other, band)| other != idx && band.name == name)
+}
+
+fn render_envelopes(ui: &mut egui::Ui, config: &mut AudioConfig) {
+    egui::CollapsingHeader::new("Envelopes")
+        .default_open(false)
+        .show(ui, |ui| {
+            ui.add_space(4.0);
+            egui::Grid::new("envelopes_grid")
+                .num_columns(4)
+                .spacing([8.0, 8.0])
+                .show(ui, |ui| {
+                    ui.label("Metric");
+                    ui.label("Attack")
+                        .on_hover_text("Lower = follows hits instantly");
+                    ui.label("Release")
+                        .on_hover_text("Higher = smoother tails between hits");
+                    ui.label("Peak Hold")
+                        .on_hover_text("How long peaks are held, 0 = off");
+                    ui.end_row();
+
+                    for (name, settings) in [
+                        ("Loudness", &mut config.loudness_envelope),
+                        ("Bass", &mut config.bass_envelope),
+                        ("Width", &mut config.stereo_width_envelope),
+                        ("Balance", &mut config.stereo_balance_envelope),
+                        ("Correlation", &mut config.phase_correlation_envelope),
+                        ("Centroid", &mut config.spectral_centroid_envelope),
+                        ("Bands", &mut config.band_envelope),
+                    ] {
+                        ui.label(name);
+                        render_envelope_settings(ui, settings);
+                        ui.end_row();
+                    }
                 });
         });
 }
 
+fn render_envelope_settings(ui: &mut egui::Ui, settings: &mut EnvelopeSettings) {
+    ui.add(
+        egui::DragValue::new(&mut settings.attack_ms)
+            .range(0.0..=1000.0)
+            .suffix(" ms"),
+    );
+    ui.add(
+        egui::DragValue::new(&mut settings.release_ms)
+            .range(0.0..=5000.0)
+            .speed(5.0)
+            .suffix(" ms"),
+    );
+    ui.add(
+        egui::DragValue::new(&mut settings.peak_hold_ms)
+            .range(0.0..=5000.0)
+            .speed(5.0)
+            .suffix(" ms"),
+    );
+}
+
+fn render_onsets_and_tempo(ui: &mut egui::Ui, config: &mut AudioConfig) {
+    egui::CollapsingHeader::new("Onsets, Tempo & Key")
+        .default_open(false)
+        .show(ui, |ui| {
+            ui.add_space(4.0);
+            egui::Grid::new("onset_settings_grid")
+                .num_columns(2)
+                .spacing([20.0, 8.0])
+                .show(ui, |ui| {
+                    ui.label("Threshold:").on_hover_text(
+                        "Flux needed above its running median (higher = only strong hits)",
+                    );
+                    ui.add(egui::Slider::new(&mut config.onset_threshold, 1.0..=4.0));
+                    ui.end_row();
+
+                    ui.label("Delta:")
+                        .on_hover_text("Minimum flux for any onset, ignores noise and silence");
+                    ui.add(egui::Slider::new(&mut config.onset_delta, 0.0..=0.5));
+                    ui.end_row();
+
+                    ui.label("Median Window:")
+                        .on_hover_text("How much history the threshold adapts to");
+                    ui.add(
+                        egui::Slider::new(&mut config.onset_median_seconds, 0.1..=2.0).suffix(" s"),
+                    );
+                    ui.end_row();
+
+                    ui.label("Min Interval:")
+                        .on_hover_text("Shortest gap between two onsets in the same band");
+                    ui.add(
+                        egui::Slider::new(&mut config.onset_min_interval_ms, 10.0..=500.0)
+                            .logarithmic(true)
+                            .suffix(" ms"),
+                    );
+                    ui.end_row();
+
+                    ui.label("Tempo Range:")
+                        .on_hover_text("BPM range searched by the beat tracker");
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut config.tempo_min_bpm)
+                                .range(40.0..=config.tempo_max_bpm - 10.0)
+                                .suffix(" BPM"),
+                        );
+                        ui.label("to");
+                        ui.add(
+                            egui::DragValue::new(&mut config.tempo_max_bpm)
+                                .range(config.tempo_min_bpm + 10.0..=240.0)
+                                .suffix(" BPM"),
+                        );
+                    });
+                    ui.end_row();
+
+                    ui.label("Phrase Length:")
+                        .on_hover_text("Bars per phrase, drops usually start a new one");
+                    egui::ComboBox::from_id_salt("phrase_length_selector")
+                        .selected_text(config.phrase_length.label())
+                        .show_ui(ui, |ui| {
+                            for length in PhraseLength::ALL {
+                                ui.selectable_value(
+                                    &mut config.phrase_length,
+                                    length,
+                                    length.label(),
+                                );
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Key Window:")
+                        .on_hover_text("How much recent harmony the key estimate averages over");
+                    ui.add(
+                        egui::Slider::new(&mut config.key_window_seconds, 2.0..=60.0)
+                            .logarithmic(true)
+                            .suffix(" s"),
+                    );
+                    ui.end_row();
+                });
+        });
+}
+
+fn render_source_separation(ui: &mut egui::Ui, config: &mut AudioConfig) {
+    egui::CollapsingHeader::new("Source Separation")
+        .default_open(false)
+        .show(ui, |ui| {
+            ui.add_space(4.0);
+            egui::Grid::new("hpss_settings_grid")
+                .num_columns(2)
+                .spacing([20.0, 8.0])
+                .show(ui, |ui| {
+                    ui.label("Enabled:")
+                        .on_hover_text("Split the sound into sustained and percussive parts");
+                    ui.checkbox(&mut config.hpss_enabled, "");
+                    ui.end_row();
+
+                    ui.label("Harmonic Window:")
+                        .on_hover_text("How long a sound must last to count as sustained");
+                    ui.add_enabled(
+                        config.hpss_enabled,
+                        egui::Slider::new(&mut config.hpss_harmonic_seconds, 0.05..=1.0)
+                            .suffix(" s"),
+                    );
+                    ui.end_row();
+
+                    ui.label("Percussive Width:")
+                        .on_hover_text("How broadband a sound must be to count as a hit");
+                    ui.add_enabled(
+                        config.hpss_enabled,
+                        egui::Slider::new(&mut config.hpss_percussive_hz, 100.0..=2000.0)
+                            .logarithmic(true)
+                            .suffix(" Hz"),
+                    );
+                    ui.end_row();
+
+                    ui.label("Percussive Onsets:").on_hover_text(
+                        "Detect onsets, drums and beats on the percussive part only",
+                    );
+                    ui.add_enabled(
+                        config.hpss_enabled,
+                        egui::Checkbox::without_text(&mut config.percussive_onsets),
+                    );
+                    ui.end_row();
+                });
+        });
+}
+
+fn render_pitch_tracking(ui: &mut egui::Ui, config: &mut AudioConfig) {
+    egui::CollapsingHeader::new("Pitch Tracking")
+        .default_open(false)
+        .show(ui, |ui| {
+            ui.add_space(4.0);
+            egui::Grid::new("pitch_settings_grid")
+                .num_columns(2)
+                .spacing([20.0, 8.0])
+                .show(ui, |ui| {
+                    ui.label("Range:")
+                        .on_hover_text("Frequencies searched for the lead's fundamental");
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut config.pitch_min_freq)
+                                .range(30.0..=config.pitch_max_freq / 2.0)
+                                .suffix(" Hz"),
+                        );
+                        ui.label("to");
+                        ui.add(
+                            egui::DragValue::new(&mut config.pitch_max_freq)
+                                .range(config.pitch_min_freq * 2.0..=4000.0)
+                                .suffix(" Hz"),
+                        );
+                    });
+                    ui.end_row();
+
+                    ui.label("Threshold:").on_hover_text(
+                        "How periodic a frame must be to count as a note (lower = stricter)",
+                    );
+                    ui.add(egui::Slider::new(&mut config.pitch_threshold, 0.05..=0.5));
+                    ui.end_row();
+                });
+        });
+}
+
+fn render_spectrum_settings(ui: &mut egui::Ui, config: &mut AudioConfig) {
+    egui::CollapsingHeader::new("Spectrum")
+        .default_open(false)
+        .show(ui, |ui| {
+            ui.add_space(4.0);
+            egui::Grid::new("spectrum_settings_grid")
+                .num_columns(2)
+                .spacing([20.0, 8.0])
+                .show(ui, |ui| {
+                    ui.label("Bands:").on_hover_text("Number of spectrum bars");
+                    ui.add(egui::Slider::new(&mut config.spectrum_bands, 8..=128));
+                    ui.end_row();
+
+                    ui.label("Scale:")
+                        .on_hover_text("How bands are spaced along the frequency axis");
+                    egui::ComboBox::from_id_salt("spectrum_scale_selector")
+                        .selected_text(config.spectrum_scale.label())
+                        .show_ui(ui, |ui| {
+                            for scale in FrequencyScale::ALL {
+                                ui.selectable_value(
+                                    &mut config.spectrum_scale,
+                                    scale,
+                                    scale.label(),
+                                );
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Min Freq:");
+                    ui.add(
+                        egui::Slider::new(&mut config.spectrum_min_freq, 20.0..=500.0)
+                            .logarithmic(true)
+                            .suffix(" Hz"),
+                    );
+                    ui.end_row();
+
+                    ui.label("Max Freq:");
+                    ui.add(
+                        egui::Slider::new(&mut config.spectrum_max_freq, 2000.0..=24000.0)
+                            .logarithmic(true)
+                            .suffix(" Hz"),
+                    );
+                    ui.end_row();
+
+                    ui.label("Floor:")
+                        .on_hover_text("Level shown as an empty bar");
+                    ui.add(
+                        egui::Slider::new(&mut config.spectrum_db_floor, -120.0..=-30.0)
+                            .suffix(" dB"),
+                    );
+                    ui.end_row();
+
+                    ui.label("Mel Bands:")
+                        .on_hover_text("Bands of the mel filterbank MFCCs are built from");
+                    ui.add(egui::Slider::new(&mut config.mel_bands, 8..=128));
+                    ui.end_row();
+
+                    ui.label("MFCCs:")
+                        .on_hover_text("Cepstral coefficients per frame");
+                    ui.add(egui::Slider::new(
+                        &mut config.mfcc_coefficients,
+                        1..=config.mel_bands,
+                    ));
+                    ui.end_row();
+                });
+        });
+}
+
+fn render_pre_filters(ui: &mut egui::Ui, config: &mut AudioConfig) {
+    egui::CollapsingHeader::new("Pre-Filters")
+        .default_open(false)
+        .show(ui, |ui| {
+            ui.add_space(4.0);
+            let mut removed = None;
+            egui::Grid::new("pre_filters_grid")
+                .num_columns(7)
+                .spacing([8.0, 8.0])
+                .show(ui, |ui| {
+                    ui.label("On");
+                    ui.label("Type");
+                    ui.label("Frequency");
+                    ui.label("Q").on_hover_text(
+                        "Higher = sharper resonance, narrower band or steeper shelf",
+                    );
+                    ui.label("Gain").on_hover_text("Shelf boost or cut");
+                    ui.end_row();
+
+                    for (idx, stage) in config.pre_filters.iter_mut().enumerate() {
+                        ui.checkbox(&mut stage.enabled, "");
+                        egui::ComboBox::from_id_salt(("pre_filter_kind", idx))
+                            .selected_text(stage.kind.label())
+                            .show_ui(ui, |ui| {
+                                for kind in FilterKind::ALL {
+                                    ui.selectable_value(&mut stage.kind, kind, kind.label());
+                                }
+                            });
+                        ui.add(
+                            egui::DragValue::new(&mut stage.frequency)
+                                .range(10.0..=20000.0)
+                                .speed(1.0)
+                                .suffix(" Hz"),
+                        );
+                        ui.add(
+                            egui::DragValue::new(&mut stage.q)
+                                .range(0.1..=30.0)
+                                .speed(0.01),
+                        );
+                        ui.add_enabled(
+                            stage.kind.has_gain(),
+                            egui::DragValue::new(&mut stage.gain_db)
+                                .range(-24.0..=24.0)
+                                .speed(0.1)
+                                .suffix(" dB"),
+                        );
+                        if ui
+                            .small_button("✖")
+                            .on_hover_text("Remove filter")
+                            .clicked()
+                        {
+                            removed = Some(idx);
+                        }
+                        ui.end_row();
+                    }
+                });
+
+            if let Some(idx) = removed {
+                config.pre_filters.remove(idx);
+            }
+
+            ui.add_space(4.0);
+            ui.horizontal(|ui| {
+                if ui.button("+ Add Filter").clicked() {
+                    config.pre_filters.push(FilterStage::new(
+                        FilterKind::HighPass,
+                        30.0,
+                        0.707,
+                        0.0,
+                    ));
+                }
+
+                ui.separator();
+
+                ui.label("Gain:")
+                    .on_hover_text("Level change after the filters, for quiet or hot inputs");
+                ui.add(egui::Slider::new(&mut config.pre_gain_db, -24.0..=24.0).suffix(" dB"));
+            });
+        });
+}
+
 fn render_audio_processing(ui: &mut egui::Ui, config: &mut AudioConfig) {
     egui::CollapsingHeader::new("Audio Processing")
         .default_open(true)
@@ -69,11 +611,43 @@ fn render_audio_processing(ui: &mut egui::Ui, config: &mut AudioConfig) {
                     ));
                     ui.end_row();
 
-                    ui.label("Update Interval:")
-                        .on_hover_text("How often to analyze (lower = smoother)");
-                    ui.add(
-                        egui::Slider::new(&mut config.update_interval_ms, 50..=500).suffix(" ms"),
-                    );
+                    ui.label("Channel Mode:")
+                        .on_hover_text("How stereo input is folded down for analysis");
+                    egui::ComboBox::from_id_salt("channel_mode_selector")
+                        .selected_text(config.channel_mode.label())
+                        .show_ui(ui, |ui| {
+                            for mode in ChannelMode::ALL {
+                                ui.selectable_value(&mut config.channel_mode, mode, mode.label());
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Window:")
+                        .on_hover_text("Window function applied before each FFT");
+                    egui::ComboBox::from_id_salt("window_function_selector")
+                        .selected_text(config.window_function.label())
+                        .show_ui(ui, |ui| {
+                            for window in WindowFunction::ALL {
+                                ui.selectable_value(
+                                    &mut config.window_function,
+                                    window,
+                                    window.label(),
+                                );
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Hop Size:")
+                        .on_hover_text("New samples per analysis step (lower = smoother)");
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::Slider::new(&mut config.hop_size, 128..=config.buffer_size)
+                                .logarithmic(true)
+                                .suffix(" samples"),
+                        );
+                        let overlap = 1.0 - config.hop_size as f32 / config.buffer_size as f32;
+                        ui.label(format!("{:.0}% overlap", overlap * 100.0));
+                    });
                     ui.end_row();
                 });
         });
@@ -0,0 +1,74 @@
+use eframe::egui;
+
+pub fn render_goniometer(ui: &mut egui::Ui, left: &[f32], right: &[f32], correlation: f32) {
+    ui.group(|ui| {
+        ui.vertical(|ui| {
+            ui.label("Stereo Field");
+
+            let size = 120.0;
+            let (response, painter) =
+                ui.allocate_painter(egui::vec2(size, size), egui::Sense::hover());
+            let rect = response.rect;
+            let center = rect.center();
+            let radius = size * 0.45;
+
+            // Draw background
+            painter.rect_filled(rect, 0.0, egui::Color32::from_gray(20));
+
+            // Draw mono (vertical) and L/R (diagonal) guides
+            let guide = egui::Stroke::new(0.5, egui::Color32::from_gray(80));
+            painter.line_segment(
+                [
+                    egui::pos2(center.x, rect.top()),
+                    egui::pos2(center.x, rect.bottom()),
+                ],
+                guide,
+            );
+            painter.line_segment([rect.left_top(), rect.right_bottom()], guide);
+            painter.line_segment([rect.right_top(), rect.left_bottom()], guide);
+
+            // Plot mid on the vertical axis and side on the horizontal axis
+            let dot_color = egui::Color32::from_rgb(100, 200, 255).gamma_multiply(0.5);
+            for (&l, &r) in left.iter().zip(right) {
+                let side = (l - r) * std::f32::consts::FRAC_1_SQRT_2;
+                let mid = (l + r) * std::f32::consts::FRAC_1_SQRT_2;
+                let x = center.x + side.clamp(-1.0, 1.0) * radius;
+                let y = center.y - mid.clamp(-1.0, 1.0) * radius;
+                painter.circle_filled(egui::pos2(x, y), 0.8, dot_color);
+            }
+
+            render_correlation_meter(ui, correlation, size);
+        });
+    });
+}
+
+fn render_correlation_meter(ui: &mut egui::Ui, correlation: f32, width: f32) {
+    let (response, painter) = ui.allocate_painter(egui::vec2(width, 10.0), egui::Sense::hover());
+    let rect = response.rect;
+
+    painter.rect_filled(rect, 0.0, egui::Color32::from_gray(20));
+
+    // Bar grows from the center: right for in-phase, left for out-of-phase
+    let center_x = rect.center().x;
+    let end_x = center_x + correlation.clamp(-1.0, 1.0) * rect.width() * 0.5;
+    let color = if correlation < 0.0 {
+        egui::Color32::RED
+    } else {
+        egui::Color32::LIGHT_GREEN
+    };
+    painter.rect_filled(
+        egui::Rect::from_x_y_ranges(center_x.min(end_x)..=center_x.max(end_x), rect.y_range()),
+        0.0,
+        color,
+    );
+
+    painter.line_segment(
+        [
+            egui::pos2(center_x, rect.top()),
+            egui::pos2(center_x, rect.bottom()),
+        ],
+        egui::Stroke::new(1.0, egui::Color32::from_gray(120)),
+    );
+
+    response.on_hover_text(format!("Phase correlation: {correlation:+.2}"));
+}
@@ -1,11 +1,20 @@
-use crate::audio::AudioMetrics;
-use crate::controller::ControllerOutput;
+use crate::audio::Drum;
+use crate::audio::mel::MEL_DB_FLOOR;
+use crate::audio::{AudioMetrics, Envelope, MeterReading};
+use crate::controller::{BEATS_PER_BAR, ControllerEvent, ControllerOutput, DropState};
 use eframe::egui;
 
+/// Lowest level shown on the dB meter; anything quieter reads as empty.
+const METER_FLOOR_DB: f32 = -60.0;
+
+/// EBU R128 target loudness, marked on the loudness bars.
+const TARGET_LUFS: f32 = -23.0;
+
 pub fn render_live_monitoring(
     ui: &mut egui::Ui,
     analyzer_metrics: &AudioMetrics,
     controller_output: &ControllerOutput,
+    controller_events: &[ControllerEvent],
 ) {
     ui.label(egui::RichText::new("Live Monitoring").size(16.0));
     ui.add_space(8.0);
@@ -13,13 +22,19 @@ pub fn render_live_monitoring(
     render_analyzer_metrics(ui, analyzer_metrics);
     ui.add_space(8.0);
 
-    render_controller_output(ui, controller_output);
+    render_loudness_meter(ui, &analyzer_metrics.meter);
+    ui.add_space(8.0);
+
+    render_controller_output(ui, controller_output, controller_events);
     ui.add_space(12.0);
 }
 
 fn render_analyzer_metrics(ui: &mut egui::Ui, metrics: &AudioMetrics) {
     ui.group(|ui| {
-        ui.colored_label(egui::Color32::LIGHT_BLUE, "Analyzer Output (Raw Metrics):");
+        ui.horizontal(|ui| {
+            ui.colored_label(egui::Color32::LIGHT_BLUE, "Analyzer Output (Raw Metrics):");
+            ui.label(format!("@ {:.1} s", metrics.time));
+        });
         ui.horizontal(|ui| {
             ui.label("Loudness:");
             ui.strong(format!("{:.1}%", metrics.loudness * 100.0));
@@ -28,11 +43,294 @@ fn render_analyzer_metrics(ui: &mut egui::Ui, metrics: &AudioMetrics) {
 
             ui.label("Bass Energy:");
             ui.strong(format!("{:.1}%", metrics.bass_energy * 100.0));
+
+            ui.separator();
+
+            ui.label("Onset Flux:");
+            ui.strong(format!("{:.3}", metrics.onset_strength));
+            if metrics.onsets.iter().any(|onset| onset.band.is_none()) {
+                ui.colored_label(egui::Color32::YELLOW, "●");
+            }
+        });
+        ui.horizontal_wrapped(|ui| {
+            for band in &metrics.band_energies {
+                let hit = metrics
+                    .onsets
+                    .iter()
+                    .any(|onset| onset.band.as_deref() == Some(band.name.as_str()));
+                let name = format!("{}:", band.name);
+                if hit {
+                    ui.colored_label(egui::Color32::YELLOW, name);
+                } else {
+                    ui.label(name);
+                }
+                ui.strong(format!("{:.0}%", band.energy * 100.0));
+            }
+        });
+        ui.horizontal_wrapped(|ui| {
+            let relative = &metrics.relative;
+            ui.label("Rank:");
+            if !relative.calibrated {
+                ui.colored_label(egui::Color32::GRAY, "calibrating...");
+                return;
+            }
+            ui.label("loudness");
+            ui.strong(format!("{:.0}%", relative.loudness * 100.0));
+            ui.label("bass");
+            ui.strong(format!("{:.0}%", relative.bass_energy * 100.0));
+            for (band, rank) in metrics.band_energies.iter().zip(&relative.band_energies) {
+                ui.label(&band.name);
+                ui.strong(format!("{:.0}%", rank * 100.0));
+            }
+        });
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Centroid:");
+            ui.strong(format!("{:.0} Hz", metrics.spectral_centroid));
+            ui.label("Spread:");
+            ui.strong(format!("{:.0} Hz", metrics.spectral_spread));
+            ui.label("Rolloff:");
+            ui.strong(format!("{:.0} Hz", metrics.spectral_rolloff));
+
+            ui.separator();
+
+            ui.label("Flatness:");
+            ui.strong(format!("{:.2}", metrics.spectral_flatness));
+            ui.label("ZCR:");
+            ui.strong(format!("{:.3}", metrics.zero_crossing_rate));
+            ui.label("Crest:");
+            ui.strong(format!("{:.1}", metrics.crest_factor));
+        });
+        ui.horizontal(|ui| {
+            let envelopes = &metrics.envelopes;
+            ui.label("Envelopes:");
+            render_envelope(ui, "loudness", envelopes.loudness);
+            render_envelope(ui, "bass", envelopes.bass_energy);
+            render_envelope(ui, "width", envelopes.stereo_width);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Balance:");
+            ui.strong(format!("{:+.2}", metrics.stereo_balance));
+
+            ui.separator();
+
+            ui.label("Correlation:");
+            ui.strong(format!("{:+.2}", metrics.phase_correlation));
+
+            ui.separator();
+
+            ui.label("Width:");
+            ui.strong(format!("{:.1}%", metrics.stereo_width * 100.0));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Harmonic:");
+            ui.add(
+                egui::ProgressBar::new(metrics.harmonic_energy)
+                    .desired_width(60.0)
+                    .desired_height(8.0),
+            );
+
+            ui.separator();
+
+            ui.label("Percussive:");
+            ui.add(
+                egui::ProgressBar::new(metrics.percussive_energy)
+                    .desired_width(60.0)
+                    .desired_height(8.0),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("Tempo:");
+            if metrics.tempo.bpm > 0.0 {
+                ui.strong(format!("{:.1} BPM", metrics.tempo.bpm));
+            } else {
+                ui.strong("--");
+            }
+
+            ui.separator();
+
+            ui.label("Confidence:");
+            ui.strong(format!("{:.0}%", metrics.tempo.confidence * 100.0));
+
+            ui.separator();
+
+            ui.label("Beat:");
+            ui.add(
+                egui::ProgressBar::new(1.0 - metrics.tempo.phase)
+                    .desired_width(60.0)
+                    .desired_height(8.0),
+            );
+        });
+        ui.horizontal(|ui| {
+            let pitch = metrics.pitch;
+            ui.label("Pitch:");
+            if pitch.voiced {
+                ui.strong(format!("{:.1} Hz", pitch.frequency));
+                ui.strong(pitch.note_name());
+            } else {
+                ui.strong("--");
+            }
+
+            ui.separator();
+
+            ui.label("Voicing:");
+            ui.strong(format!("{:.0}%", pitch.confidence * 100.0));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Key:");
+            match metrics.key {
+                Some(estimate) => {
+                    ui.strong(estimate.key.name());
+                    ui.label(format!("({:.0}%)", estimate.confidence * 100.0));
+                }
+                None => {
+                    ui.strong("--");
+                }
+            }
+
+            ui.separator();
+
+            ui.label("Chroma:");
+            render_level_strip(ui, &metrics.chroma, 96.0).on_hover_text("Pitch classes C to B");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Mel:");
+            let levels: Vec<f32> = metrics
+                .mel_energies
+                .iter()
+                .map(|db| 1.0 - db / MEL_DB_FLOOR)
+                .collect();
+            render_level_strip(ui, &levels, 120.0);
+
+            ui.separator();
+
+            ui.label("MFCC:");
+            let shown: Vec<String> = metrics
+                .mfcc
+                .iter()
+                .skip(1)
+                .take(4)
+                .map(|c| format!("{c:+.0}"))
+                .collect();
+            ui.strong(shown.join(" "))
+                .on_hover_text("Coefficients 1 to 4: spectral tilt and shape");
+        });
+    });
+}
+
+/// Small bar graph of 0-1 levels, `width` points wide.
+fn render_level_strip(ui: &mut egui::Ui, levels: &[f32], width: f32) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(width, 16.0), egui::Sense::hover());
+    let painter = ui.painter();
+    painter.rect_filled(rect, 0.0, egui::Color32::from_gray(20));
+
+    let bar_width = rect.width() / levels.len().max(1) as f32;
+    let gap = (bar_width * 0.2).min(1.0);
+    for (i, &level) in levels.iter().enumerate() {
+        let left = rect.left() + i as f32 * bar_width;
+        painter.rect_filled(
+            egui::Rect::from_min_max(
+                egui::pos2(
+                    left + gap,
+                    rect.bottom() - level.clamp(0.0, 1.0) * rect.height(),
+                ),
+                egui::pos2(left + bar_width - gap, rect.bottom()),
+            ),
+            0.0,
+            egui::Color32::from_rgb(100, 200, 255),
+        );
+    }
+    response
+}
+
+fn render_loudness_meter(ui: &mut egui::Ui, meter: &MeterReading) {
+    ui.group(|ui| {
+        ui.horizontal(|ui| {
+            ui.colored_label(egui::Color32::LIGHT_BLUE, "Loudness Meter:");
+            ui.label("Integrated");
+            ui.strong(format_db(meter.integrated_lufs, "LUFS"));
         });
+        egui::Grid::new("loudness_meter_grid")
+            .num_columns(3)
+            .spacing([8.0, 4.0])
+            .show(ui, |ui| {
+                for (name, level, unit, target) in [
+                    ("Momentary", meter.momentary_lufs, "LUFS", Some(TARGET_LUFS)),
+                    (
+                        "Short-term",
+                        meter.short_term_lufs,
+                        "LUFS",
+                        Some(TARGET_LUFS),
+                    ),
+                    ("Peak", meter.peak_dbfs, "dBFS", None),
+                    ("True Peak", meter.true_peak_dbfs, "dBTP", None),
+                ] {
+                    ui.label(name);
+                    render_db_bar(ui, level, target);
+                    ui.strong(format_db(level, unit));
+                    ui.end_row();
+                }
+            });
     });
 }
 
-fn render_controller_output(ui: &mut egui::Ui, output: &ControllerOutput) {
+/// Horizontal bar from [`METER_FLOOR_DB`] to 0 dB, green up to -18 dB,
+/// yellow up to -6 dB and red above, with an optional target mark.
+fn render_db_bar(ui: &mut egui::Ui, level_db: f32, target_db: Option<f32>) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(160.0, 10.0), egui::Sense::hover());
+    let painter = ui.painter();
+    painter.rect_filled(rect, 0.0, egui::Color32::from_gray(20));
+
+    let x = |db: f32| rect.left() + rect.width() * (1.0 - db / METER_FLOOR_DB).clamp(0.0, 1.0);
+    let level = x(level_db);
+    for (from, to, color) in [
+        (METER_FLOOR_DB, -18.0, egui::Color32::from_rgb(60, 200, 90)),
+        (-18.0, -6.0, egui::Color32::from_rgb(230, 200, 40)),
+        (-6.0, 0.0, egui::Color32::from_rgb(230, 50, 40)),
+    ] {
+        let right = level.min(x(to));
+        if right > x(from) {
+            painter.rect_filled(
+                egui::Rect::from_x_y_ranges(x(from)..=right, rect.y_range()),
+                0.0,
+                color,
+            );
+        }
+    }
+
+    if let Some(target) = target_db {
+        painter.vline(
+            x(target),
+            rect.y_range(),
+            egui::Stroke::new(1.0, egui::Color32::WHITE),
+        );
+    }
+}
+
+fn format_db(level_db: f32, unit: &str) -> String {
+    if level_db.is_finite() {
+        format!("{level_db:.1} {unit}")
+    } else {
+        format!("-inf {unit}")
+    }
+}
+
+/// Smoothed value, with the held peak as long as it sits above it.
+fn render_envelope(ui: &mut egui::Ui, name: &str, envelope: Envelope) {
+    ui.label(name);
+    ui.strong(format!("{:.0}%", envelope.value * 100.0));
+    if envelope.peak > envelope.value {
+        ui.colored_label(
+            egui::Color32::GRAY,
+            format!("peak {:.0}%", envelope.peak * 100.0),
+        );
+    }
+}
+
+fn render_controller_output(
+    ui: &mut egui::Ui,
+    output: &ControllerOutput,
+    events: &[ControllerEvent],
+) {
     ui.group(|ui| {
         ui.colored_label(egui::Color32::LIGHT_GREEN, "Controller Output:");
         ui.horizontal(|ui| {
@@ -41,10 +339,89 @@ fn render_controller_output(ui: &mut egui::Ui, output: &ControllerOutput) {
 
             ui.separator();
 
-            if output.is_drop {
-                ui.colored_label(egui::Color32::RED, "DROP DETECTED");
-            } else {
-                ui.colored_label(egui::Color32::GRAY, "Normal");
+            ui.label("Width:");
+            ui.strong(format!("{:.1}%", output.stereo_width * 100.0));
+
+            ui.separator();
+
+            let state = output.drop_state;
+            let color = match state {
+                DropState::Drop => egui::Color32::RED,
+                DropState::Sustain => egui::Color32::LIGHT_RED,
+                DropState::BuildUp => egui::Color32::ORANGE,
+                DropState::Idle | DropState::Release => egui::Color32::GRAY,
+            };
+            ui.colored_label(color, state.label().to_uppercase());
+
+            ui.separator();
+
+            let beat = events.iter().find_map(|event| match *event {
+                ControllerEvent::Beat { downbeat, .. } => Some(downbeat),
+                _ => None,
+            });
+            match beat {
+                Some(true) => ui.colored_label(egui::Color32::GOLD, "DOWNBEAT"),
+                Some(false) => ui.colored_label(egui::Color32::YELLOW, "BEAT"),
+                None => ui.colored_label(egui::Color32::GRAY, "beat"),
+            };
+        });
+        ui.horizontal(|ui| {
+            let position = output.bar_position;
+            ui.label("Bar:");
+            ui.strong(format!("{}/{}", position.beat_in_bar, BEATS_PER_BAR));
+
+            ui.separator();
+
+            ui.label("Phrase:");
+            ui.strong(format!("bar {}", position.bar_in_phrase));
+
+            ui.separator();
+
+            ui.label("Drums:");
+            for drum in Drum::ALL {
+                let velocity = events.iter().find_map(|event| match *event {
+                    ControllerEvent::Hit {
+                        drum: hit,
+                        velocity,
+                        ..
+                    } if hit == drum => Some(velocity),
+                    _ => None,
+                });
+                match velocity {
+                    Some(velocity) => ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!("{} {:.0}%", drum.label().to_uppercase(), velocity * 100.0),
+                    ),
+                    None => ui.colored_label(egui::Color32::GRAY, drum.label().to_lowercase()),
+                };
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Brightness:");
+            ui.add(
+                egui::ProgressBar::new(output.brightness)
+                    .desired_width(60.0)
+                    .desired_height(8.0),
+            );
+
+            ui.separator();
+
+            ui.label("Noisiness:");
+            ui.add(
+                egui::ProgressBar::new(output.noisiness)
+                    .desired_width(60.0)
+                    .desired_height(8.0),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("Build-up:");
+            ui.add(
+                egui::ProgressBar::new(output.buildup_intensity)
+                    .desired_width(60.0)
+                    .desired_height(8.0),
+            );
+            if let Some(seconds) = output.time_to_drop {
+                ui.colored_label(egui::Color32::ORANGE, format!("drop in {seconds:.1}s"));
             }
         });
     });
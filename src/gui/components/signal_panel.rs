@@ -0,0 +1,97 @@
+use eframe::egui;
+
+use crate::audio::{SignalKind, SignalSettings};
+
+pub fn render_signal_settings(ui: &mut egui::Ui, settings: &mut SignalSettings) {
+    egui::Grid::new("signal_settings_grid")
+        .num_columns(2)
+        .spacing([20.0, 8.0])
+        .show(ui, |ui| {
+            ui.label("Signal:");
+            egui::ComboBox::from_id_salt("signal_kind_selector")
+                .selected_text(settings.kind.label())
+                .show_ui(ui, |ui| {
+                    for kind in SignalKind::ALL {
+                        ui.selectable_value(&mut settings.kind, kind, kind.label());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Sample Rate:");
+            egui::ComboBox::from_id_salt("signal_rate_selector")
+                .selected_text(format!("{} Hz", settings.sample_rate))
+                .show_ui(ui, |ui| {
+                    for rate in [22050, 44100, 48000, 96000] {
+                        ui.selectable_value(&mut settings.sample_rate, rate, format!("{rate} Hz"));
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Amplitude:");
+            ui.add(egui::Slider::new(&mut settings.amplitude, 0.0..=1.0));
+            ui.end_row();
+
+            match settings.kind {
+                SignalKind::Sine => {
+                    ui.label("Frequency:");
+                    ui.add(
+                        egui::Slider::new(&mut settings.frequency, 20.0..=20000.0)
+                            .logarithmic(true)
+                            .suffix(" Hz"),
+                    );
+                    ui.end_row();
+                }
+                SignalKind::Sweep => {
+                    ui.label("Start Frequency:");
+                    ui.add(
+                        egui::Slider::new(&mut settings.frequency, 20.0..=20000.0)
+                            .logarithmic(true)
+                            .suffix(" Hz"),
+                    );
+                    ui.end_row();
+
+                    ui.label("End Frequency:");
+                    ui.add(
+                        egui::Slider::new(&mut settings.sweep_end_frequency, 20.0..=20000.0)
+                            .logarithmic(true)
+                            .suffix(" Hz"),
+                    );
+                    ui.end_row();
+
+                    ui.label("Sweep Duration:");
+                    ui.add(egui::Slider::new(&mut settings.sweep_seconds, 1.0..=60.0).suffix(" s"));
+                    ui.end_row();
+                }
+                SignalKind::WhiteNoise | SignalKind::PinkNoise => {}
+                SignalKind::DrumLoop => {
+                    ui.label("Tempo:");
+                    ui.add(egui::Slider::new(&mut settings.bpm, 60.0..=200.0).suffix(" BPM"));
+                    ui.end_row();
+                }
+                SignalKind::KickPattern => {
+                    ui.label("Tempo:");
+                    ui.add(egui::Slider::new(&mut settings.bpm, 60.0..=200.0).suffix(" BPM"));
+                    ui.end_row();
+
+                    ui.label("Break Length:")
+                        .on_hover_text("Bars of hats-only breakdown before each drop");
+                    ui.add(egui::Slider::new(&mut settings.break_bars, 0..=32).suffix(" bars"));
+                    ui.end_row();
+
+                    ui.label("Drop Length:")
+                        .on_hover_text("Bars of kick + sub-bass after each breakdown");
+                    ui.add(egui::Slider::new(&mut settings.drop_bars, 0..=32).suffix(" bars"));
+                    ui.end_row();
+
+                    ui.label("Riser Length:").on_hover_text(
+                        "Bars at the end of each breakdown that build up to the drop",
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut settings.riser_bars, 0..=settings.break_bars)
+                            .suffix(" bars"),
+                    );
+                    ui.end_row();
+                }
+            }
+        });
+}
@@ -0,0 +1,46 @@
+use crate::audio::band_spectrum::BandSpectrum;
+use eframe::egui;
+
+pub fn render_spectrum(ui: &mut egui::Ui, spectrum: &BandSpectrum) {
+    ui.group(|ui| {
+        ui.label("Spectrum");
+
+        let desired_height = 100.0;
+        let (response, painter) = ui.allocate_painter(
+            egui::vec2(ui.available_width(), desired_height),
+            egui::Sense::hover(),
+        );
+
+        let rect = response.rect;
+
+        // Draw background
+        painter.rect_filled(rect, 0.0, egui::Color32::from_gray(20));
+
+        if spectrum.normalized.is_empty() {
+            return;
+        }
+
+        let bar_width = rect.width() / spectrum.normalized.len() as f32;
+        for (i, &level) in spectrum.normalized.iter().enumerate() {
+            let left = rect.left() + i as f32 * bar_width;
+            let top = rect.bottom() - level * rect.height();
+            painter.rect_filled(
+                egui::Rect::from_min_max(
+                    egui::pos2(left + 1.0, top),
+                    egui::pos2(left + bar_width - 1.0, rect.bottom()),
+                ),
+                0.0,
+                egui::Color32::from_rgb(100, 200, 255),
+            );
+        }
+
+        // Show the band under the cursor
+        if let Some(pos) = response.hover_pos() {
+            let band = (((pos.x - rect.left()) / bar_width) as usize).min(spectrum.db.len() - 1);
+            response.on_hover_text(format!(
+                "{:.0} Hz: {:.1} dB",
+                spectrum.centers_hz[band], spectrum.db[band]
+            ));
+        }
+    });
+}
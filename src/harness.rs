@@ -0,0 +1,1012 @@
+//! Deterministic offline harness for the analysis pipeline.
+//!
+//! Pushes sample blocks through [`AudioAnalyzer`] and [`Controller`] on a
+//! simulated clock, without threads, sleeps or audio devices, so results only
+//! depend on the input signal and the config. Like the live pipeline, every
+//! full hop of audio produces exactly one analysis step.
+
+use crate::audio::signal_generator::{SignalGenerator, SignalKind, SignalSettings};
+use crate::audio::{AudioAnalyzer, AudioMetrics};
+use crate::config::AudioConfig;
+use crate::controller::{Controller, ControllerEvent, ControllerOutput};
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+/// One analysis step of the harness.
+#[derive(Clone, Debug)]
+pub struct Frame {
+    /// Simulated stream time in seconds at the end of the analyzed window.
+    pub time: f64,
+    pub metrics: AudioMetrics,
+    pub output: ControllerOutput,
+}
+
+pub struct OfflineHarness {
+    analyzer: AudioAnalyzer,
+    controller: Controller,
+    sample_rate: u32,
+    channels: usize,
+    frames_processed: u64,
+}
+
+impl OfflineHarness {
+    pub fn new(config: AudioConfig, sample_rate: u32, channels: usize) -> Self {
+        let config = Arc::new(RwLock::new(config));
+        let mut analyzer = AudioAnalyzer::new(config.clone());
+        analyzer.set_format(sample_rate, channels);
+
+        Self {
+            analyzer,
+            controller: Controller::new(config),
+            sample_rate,
+            channels,
+            frames_processed: 0,
+        }
+    }
+
+    /// Simulated time in seconds of all audio fed so far.
+    pub fn time(&self) -> f64 {
+        self.frames_processed as f64 / self.sample_rate as f64
+    }
+
+    /// Feeds one block of interleaved samples and runs every analysis step
+    /// it completes.
+    pub fn process_block(&mut self, samples: &[f32]) -> Vec<Frame> {
+        self.analyzer.add_samples(samples);
+        self.frames_processed += (samples.len() / self.channels) as u64;
+
+        let mut frames = Vec::new();
+        while let Some(metrics) = self.analyzer.next_hop() {
+            let output = self.controller.process(metrics.clone());
+            frames.push(Frame {
+                time: metrics.time,
+                metrics,
+                output,
+            });
+        }
+        frames
+    }
+
+    /// Runs a mono generator for `seconds`, feeding it in `block_frames` chunks.
+    pub fn run_generator(
+        &mut self,
+        generator: &mut SignalGenerator,
+        seconds: f64,
+        block_frames: usize,
+    ) -> Vec<Frame> {
+        assert_eq!(self.channels, 1, "generators produce mono signals");
+
+        let mut block = vec![0.0; block_frames];
+        let mut frames = Vec::new();
+        while self.time() < seconds {
+            generator.fill(&mut block);
+            frames.extend(self.process_block(&block));
+        }
+        frames
+    }
+}
+
+/// Renders frames as one whitespace-separated row per step.
+pub fn render_frames(frames: &[Frame]) -> String {
+    let mut out = String::from("# time loudness bass_energy drop_state\n");
+    for frame in frames {
+        writeln!(
+            out,
+            "{:.3} {:.4} {:.4} {}",
+            frame.time,
+            frame.metrics.loudness,
+            frame.metrics.bass_energy,
+            frame.output.drop_state.label()
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// Lists drop events as `start`/`end` lines with their time.
+pub fn render_drop_events(frames: &[Frame]) -> String {
+    let mut out = String::new();
+    for event in frames.iter().flat_map(|frame| &frame.output.events) {
+        match event {
+            ControllerEvent::DropStarted { time } => writeln!(out, "start {time:.3}").unwrap(),
+            ControllerEvent::DropEnded { time } => writeln!(out, "end {time:.3}").unwrap(),
+            ControllerEvent::Beat { .. }
+            | ControllerEvent::Hit { .. }
+            | ControllerEvent::Onset { .. } => {}
+        }
+    }
+    out
+}
+
+/// Lists every onset as `time band strength`, `all` for full-spectrum onsets.
+pub fn render_onsets(frames: &[Frame]) -> String {
+    let mut out = String::from("# time band strength\n");
+    for onset in frames.iter().flat_map(|frame| &frame.metrics.onsets) {
+        writeln!(
+            out,
+            "{:.3} {} {:.3}",
+            onset.time,
+            onset.band.as_deref().unwrap_or("all"),
+            onset.strength
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// Compares `actual` against the golden file `name`, allowing a small numeric
+/// tolerance. Set `UPDATE_GOLDEN=1` to rewrite the file instead.
+pub fn assert_golden(name: &str, actual: &str) {
+    let path = golden_path(name);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "Missing golden file {} ({e}), run with UPDATE_GOLDEN=1",
+            path.display()
+        )
+    });
+
+    let expected_lines: Vec<&str> = expected.lines().collect();
+    let actual_lines: Vec<&str> = actual.lines().collect();
+    assert_eq!(
+        expected_lines.len(),
+        actual_lines.len(),
+        "{name}: line count differs"
+    );
+
+    for (idx, (expected, actual)) in expected_lines.iter().zip(&actual_lines).enumerate() {
+        let expected_fields: Vec<&str> = expected.split_whitespace().collect();
+        let actual_fields: Vec<&str> = actual.split_whitespace().collect();
+        let matches = expected_fields.len() == actual_fields.len()
+            && expected_fields
+                .iter()
+                .zip(&actual_fields)
+                .all(|(e, a)| fields_match(e, a));
+        assert!(
+            matches,
+            "{name}:{}: expected `{expected}`, got `{actual}`",
+            idx + 1
+        );
+    }
+}
+
+fn fields_match(expected: &str, actual: &str) -> bool {
+    match (expected.parse::<f64>(), actual.parse::<f64>()) {
+        (Ok(e), Ok(a)) => (e - a).abs() <= 2e-3,
+        _ => expected == actual,
+    }
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("testdata")
+        .join("golden")
+        .join(name)
+}
+
+/// Short kick pattern: 2 bars of break, then 2 bars of drop, repeated.
+pub fn kick_pattern_settings() -> SignalSettings {
+    SignalSettings {
+        kind: SignalKind::KickPattern,
+        amplitude: 0.9,
+        break_bars: 2,
+        drop_bars: 2,
+        riser_bars: 0,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::Drum;
+    use crate::config::{EnvelopeSettings, FilterKind, FilterStage, PhraseLength, ThresholdMode};
+    use crate::controller::{BEATS_PER_BAR, BarPosition};
+
+    const SAMPLE_RATE: u32 = 44100;
+    const BLOCK_FRAMES: usize = 512;
+
+    fn run_signal_in_blocks(
+        settings: SignalSettings,
+        seconds: f64,
+        block_frames: usize,
+    ) -> Vec<Frame> {
+        let mut harness = OfflineHarness::new(AudioConfig::default(), SAMPLE_RATE, 1);
+        let mut generator = SignalGenerator::new(SignalSettings {
+            sample_rate: SAMPLE_RATE,
+            ..settings
+        });
+        harness.run_generator(&mut generator, seconds, block_frames)
+    }
+
+    fn run_signal(settings: SignalSettings, seconds: f64) -> Vec<Frame> {
+        run_signal_in_blocks(settings, seconds, BLOCK_FRAMES)
+    }
+
+    #[test]
+    fn sine_440_metrics_match_golden() {
+        let frames = run_signal(
+            SignalSettings {
+                kind: SignalKind::Sine,
+                frequency: 440.0,
+                ..Default::default()
+            },
+            2.0,
+        );
+        assert_golden("sine_440.txt", &render_frames(&frames));
+    }
+
+    #[test]
+    fn sine_60_metrics_match_golden() {
+        let frames = run_signal(
+            SignalSettings {
+                kind: SignalKind::Sine,
+                frequency: 60.0,
+                ..Default::default()
+            },
+            2.0,
+        );
+        assert_golden("sine_60.txt", &render_frames(&frames));
+    }
+
+    #[test]
+    fn sine_energy_lands_in_the_matching_band() {
+        for (frequency, expected) in [(40.0, "sub"), (100.0, "kick"), (8000.0, "air")] {
+            let frames = run_signal(
+                SignalSettings {
+                    kind: SignalKind::Sine,
+                    frequency,
+                    ..Default::default()
+                },
+                0.5,
+            );
+            let last = &frames.last().unwrap().metrics.band_energies;
+            let loudest = last
+                .iter()
+                .max_by(|a, b| a.energy.total_cmp(&b.energy))
+                .unwrap();
+            assert_eq!(loudest.name, expected, "{frequency} Hz: {last:?}");
+        }
+    }
+
+    #[test]
+    fn white_noise_metrics_match_golden() {
+        let frames = run_signal(
+            SignalSettings {
+                kind: SignalKind::WhiteNoise,
+                ..Default::default()
+            },
+            2.0,
+        );
+        assert_golden("white_noise.txt", &render_frames(&frames));
+    }
+
+    #[test]
+    fn pink_noise_metrics_match_golden() {
+        let frames = run_signal(
+            SignalSettings {
+                kind: SignalKind::PinkNoise,
+                ..Default::default()
+            },
+            2.0,
+        );
+        assert_golden("pink_noise.txt", &render_frames(&frames));
+    }
+
+    #[test]
+    fn kick_pattern_metrics_and_drops_match_golden() {
+        let frames = run_signal(kick_pattern_settings(), 8.0);
+        assert_golden("kick_pattern.txt", &render_frames(&frames));
+        assert_golden("kick_pattern_drops.txt", &render_drop_events(&frames));
+    }
+
+    #[test]
+    fn kick_pattern_raises_one_drop_per_drop_section() {
+        // Sections longer than the drop hold time and cooldown
+        let settings = SignalSettings {
+            break_bars: 4,
+            drop_bars: 4,
+            ..kick_pattern_settings()
+        };
+        let section_seconds = 4.0 * 4.0 * 60.0 / settings.bpm as f64;
+        let config = AudioConfig::default();
+        // Events lag the audio by up to one analysis window plus one hop,
+        // and the end additionally waits for the release time
+        let latency = (config.buffer_size + config.hop_size) as f64 / SAMPLE_RATE as f64;
+        let release_seconds = config.drop_release_seconds as f64;
+        let frames = run_signal(settings, 4.0 * section_seconds + 2.0);
+
+        let events: Vec<(ControllerEvent, f64)> = frames
+            .iter()
+            .flat_map(|f| f.output.events.clone())
+            .filter_map(|event| match event {
+                ControllerEvent::DropStarted { time } | ControllerEvent::DropEnded { time } => {
+                    Some((event, time))
+                }
+                ControllerEvent::Beat { .. }
+                | ControllerEvent::Hit { .. }
+                | ControllerEvent::Onset { .. } => None,
+            })
+            .collect();
+        let mut expected = Vec::new();
+        for drop in [1.0, 3.0] {
+            let start = drop * section_seconds;
+            let end = start + section_seconds;
+            expected.push((start, start + latency));
+            expected.push((end, end + release_seconds + latency));
+        }
+
+        assert_eq!(events.len(), expected.len(), "{events:?}");
+        for (&(event, time), (earliest, latest)) in events.iter().zip(expected) {
+            assert!(
+                (earliest..=latest).contains(&time),
+                "{event:?} outside {earliest:.3}..{latest:.3}"
+            );
+        }
+        assert!(matches!(events[0].0, ControllerEvent::DropStarted { .. }));
+        assert!(matches!(events[1].0, ControllerEvent::DropEnded { .. }));
+    }
+
+    #[test]
+    fn kick_pattern_onsets_match_golden() {
+        let frames = run_signal(kick_pattern_settings(), 8.0);
+        assert_golden("kick_pattern_onsets.txt", &render_onsets(&frames));
+    }
+
+    #[test]
+    fn kick_pattern_onsets_follow_the_pattern() {
+        let settings = kick_pattern_settings();
+        let beat_seconds = 60.0 / settings.bpm as f64;
+        let break_seconds = settings.break_bars as f64 * 4.0 * beat_seconds;
+        let drop_seconds = settings.drop_bars as f64 * 4.0 * beat_seconds;
+        // Onsets are reported once the hit is inside the analysis window
+        let window_seconds = AudioConfig::default().buffer_size as f64 / SAMPLE_RATE as f64;
+        let frames = run_signal(settings, break_seconds + drop_seconds);
+        let onsets: Vec<_> = frames.iter().flat_map(|f| &f.metrics.onsets).collect();
+        let detected_within = |band: Option<&str>, hit: f64| {
+            onsets.iter().any(|onset| {
+                onset.band.as_deref() == band
+                    && (0.0..=window_seconds).contains(&(onset.time - hit))
+            })
+        };
+
+        // Break: only the offbeat hats
+        let hats: Vec<f64> = (0..)
+            .map(|beat| (beat as f64 + 0.5) * beat_seconds)
+            .take_while(|&t| t < break_seconds)
+            .collect();
+        for &hat in &hats {
+            assert!(detected_within(None, hat), "missed hat at {hat:.3}s");
+        }
+        for onset in onsets
+            .iter()
+            .filter(|o| o.band.is_none() && o.time < break_seconds)
+        {
+            assert!(
+                hats.iter()
+                    .any(|&hat| (0.0..=window_seconds).contains(&(onset.time - hat))),
+                "onset at {:.3}s without a hat",
+                onset.time
+            );
+        }
+
+        // Drop: every kick shows up in the kick band
+        let kicks = (0..)
+            .map(|beat| break_seconds + beat as f64 * beat_seconds)
+            .take_while(|&t| t < break_seconds + drop_seconds - window_seconds);
+        for kick in kicks {
+            assert!(
+                detected_within(Some("kick"), kick),
+                "missed kick at {kick:.3}s"
+            );
+        }
+    }
+
+    /// Kick pattern that stays in the drop, at `bpm`.
+    fn steady_kicks(bpm: f32) -> SignalGenerator {
+        SignalGenerator::new(SignalSettings {
+            sample_rate: SAMPLE_RATE,
+            bpm,
+            break_bars: 0,
+            drop_bars: 64,
+            ..kick_pattern_settings()
+        })
+    }
+
+    #[test]
+    fn beat_tracker_locks_onto_the_kicks() {
+        let mut harness = OfflineHarness::new(AudioConfig::default(), SAMPLE_RATE, 1);
+        let frames = harness.run_generator(&mut steady_kicks(128.0), 12.0, BLOCK_FRAMES);
+        let beat_seconds = 60.0 / 128.0;
+
+        let settled: Vec<_> = frames.iter().filter(|f| f.time > 6.0).collect();
+        for frame in &settled {
+            let tempo = frame.metrics.tempo;
+            assert!(
+                (tempo.bpm - 128.0).abs() < 0.5,
+                "{tempo:?} at {:.3}s",
+                frame.time
+            );
+            assert!(tempo.confidence > 0.3, "{tempo:?} at {:.3}s", frame.time);
+        }
+
+        let beats: Vec<_> = settled.iter().filter_map(|f| f.metrics.beat).collect();
+        // One beat per kick, none skipped or doubled
+        for pair in beats.windows(2) {
+            let interval = pair[1].time - pair[0].time;
+            assert!(
+                (interval - beat_seconds).abs() < 0.05,
+                "{interval:.3}s between beats"
+            );
+        }
+        for beat in beats {
+            let from_kick = wrap_seconds(beat.time, beat_seconds);
+            assert!(
+                from_kick.abs() < 0.05,
+                "beat {:.3}s off the kick",
+                beat.time
+            );
+        }
+    }
+
+    #[test]
+    fn beat_tracker_follows_a_tempo_change() {
+        let mut harness = OfflineHarness::new(AudioConfig::default(), SAMPLE_RATE, 1);
+        let before = harness.run_generator(&mut steady_kicks(124.0), 10.0, BLOCK_FRAMES);
+        let after = harness.run_generator(&mut steady_kicks(128.0), 20.0, BLOCK_FRAMES);
+
+        let bpm_before = before.last().unwrap().metrics.tempo.bpm;
+        let bpm_after = after.last().unwrap().metrics.tempo.bpm;
+        assert!((bpm_before - 124.0).abs() < 0.5, "{bpm_before} BPM");
+        assert!((bpm_after - 128.0).abs() < 0.5, "{bpm_after} BPM");
+    }
+
+    #[test]
+    fn drops_land_on_the_first_bar_of_a_phrase() {
+        let config = AudioConfig {
+            phrase_length: PhraseLength::Bars8,
+            ..Default::default()
+        };
+        let mut harness = OfflineHarness::new(config, SAMPLE_RATE, 1);
+        // 8-bar cycles: 4 bars of break, then a 4-bar drop
+        let settings = SignalSettings {
+            sample_rate: SAMPLE_RATE,
+            break_bars: 4,
+            drop_bars: 4,
+            ..kick_pattern_settings()
+        };
+        let beat_seconds = 60.0 / settings.bpm as f64;
+        let frames = harness.run_generator(&mut SignalGenerator::new(settings), 40.0, BLOCK_FRAMES);
+
+        // The first break locks onto the offbeat hats; counting settles once
+        // the first drop has corrected the phase and placed the downbeat
+        let beats = frames.iter().filter(|f| f.time > 15.0).flat_map(|f| {
+            f.output
+                .events
+                .iter()
+                .filter_map(move |event| match *event {
+                    ControllerEvent::Beat { downbeat, .. } => Some((f, downbeat)),
+                    _ => None,
+                })
+        });
+        for (frame, downbeat) in beats {
+            let beat = (frame.time / beat_seconds).round() as usize;
+            let bar = beat / BEATS_PER_BAR;
+            let expected = BarPosition {
+                beat_in_bar: beat % BEATS_PER_BAR + 1,
+                bar_in_phrase: (bar + 4) % 8 + 1,
+            };
+            assert_eq!(frame.output.bar_position, expected, "at {:.3}s", frame.time);
+            assert_eq!(downbeat, expected.beat_in_bar == 1, "at {:.3}s", frame.time);
+        }
+    }
+
+    #[test]
+    fn buildups_are_detected_before_each_drop() {
+        let config = AudioConfig {
+            phrase_length: PhraseLength::Bars8,
+            ..Default::default()
+        };
+        let mut harness = OfflineHarness::new(config, SAMPLE_RATE, 1);
+        // 8-bar cycles: 3 quiet bars, a 3-bar riser, then a 2-bar drop
+        let settings = SignalSettings {
+            sample_rate: SAMPLE_RATE,
+            break_bars: 6,
+            drop_bars: 2,
+            riser_bars: 3,
+            ..kick_pattern_settings()
+        };
+        let bar_seconds = 4.0 * 60.0 / settings.bpm as f64;
+        let cycle = 8.0 * bar_seconds;
+        let cycles = 6;
+        let frames = harness.run_generator(
+            &mut SignalGenerator::new(settings),
+            cycles as f64 * cycle,
+            BLOCK_FRAMES,
+        );
+
+        for n in 0..cycles {
+            let start = n as f64 * cycle;
+            let riser = start + 3.0 * bar_seconds;
+            let drop = start + 6.0 * bar_seconds;
+            let within =
+                |from: f64, to: f64| frames.iter().filter(move |f| f.time >= from && f.time < to);
+            let mean = |from: f64, to: f64| {
+                let values: Vec<f32> = within(from, to)
+                    .map(|f| f.output.buildup_intensity)
+                    .collect();
+                values.iter().sum::<f32>() / values.len() as f32
+            };
+
+            let quiet = mean(start + 1.0, riser);
+            let building = mean(riser + bar_seconds, drop);
+            let dropping = mean(drop + 1.0, start + cycle);
+            assert!(building > 0.3, "cycle {n}: build-up intensity {building}");
+            assert!(quiet < 0.1, "cycle {n}: break intensity {quiet}");
+            assert!(dropping < 0.1, "cycle {n}: drop intensity {dropping}");
+
+            // Phrase starts are placed once the first drop has been heard
+            if n == 0 {
+                continue;
+            }
+            let predictions: Vec<(f64, f64)> = within(riser, drop)
+                .filter_map(|f| Some((f.time, f.time + f.output.time_to_drop? as f64)))
+                .collect();
+            assert!(!predictions.is_empty(), "cycle {n}: no drop predicted");
+            for (time, predicted) in predictions {
+                assert!(
+                    (predicted - drop).abs() < 0.5,
+                    "at {time:.3}s: drop predicted at {predicted:.3}s, lands at {drop:.3}s"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn adaptive_thresholds_find_drops_in_a_quiet_signal() {
+        // Too quiet for the absolute thresholds to ever see a drop
+        let settings = SignalSettings {
+            sample_rate: SAMPLE_RATE,
+            amplitude: 0.1,
+            break_bars: 4,
+            drop_bars: 4,
+            ..kick_pattern_settings()
+        };
+        let section_seconds = 4.0 * 4.0 * 60.0 / settings.bpm as f64;
+        let drop_starts = |threshold_mode| {
+            let config = AudioConfig {
+                threshold_mode,
+                adaptive_window_seconds: 4.0 * section_seconds as f32,
+                ..Default::default()
+            };
+            let latency = (config.buffer_size + config.hop_size) as f64 / SAMPLE_RATE as f64;
+            let mut harness = OfflineHarness::new(config, SAMPLE_RATE, 1);
+            let frames = harness.run_generator(
+                &mut SignalGenerator::new(settings.clone()),
+                8.0 * section_seconds,
+                BLOCK_FRAMES,
+            );
+            let starts: Vec<f64> = frames
+                .iter()
+                .flat_map(|f| &f.output.events)
+                .filter_map(|event| match event {
+                    ControllerEvent::DropStarted { time } => Some(*time),
+                    ControllerEvent::DropEnded { .. }
+                    | ControllerEvent::Beat { .. }
+                    | ControllerEvent::Hit { .. }
+                    | ControllerEvent::Onset { .. } => None,
+                })
+                .collect();
+            (starts, latency)
+        };
+
+        let (starts, _) = drop_starts(ThresholdMode::Absolute);
+        assert!(starts.is_empty(), "{starts:?}");
+
+        // Calibrated after half the window, i.e. one break and one drop in,
+        // so every later drop is found
+        let (starts, latency) = drop_starts(ThresholdMode::Adaptive);
+        assert_eq!(starts.len(), 3, "{starts:?}");
+        for (time, drop) in starts.iter().zip([3.0, 5.0, 7.0]) {
+            let start = drop * section_seconds;
+            assert!(
+                (start..=start + latency).contains(time),
+                "drop start {time:.3} outside {start:.3}..{:.3}",
+                start + latency
+            );
+        }
+    }
+
+    #[test]
+    fn envelopes_smooth_loudness_and_hold_its_peaks() {
+        let run = |loudness_envelope| {
+            let config = AudioConfig {
+                loudness_envelope,
+                ..Default::default()
+            };
+            let mut harness = OfflineHarness::new(config, SAMPLE_RATE, 1);
+            harness.run_generator(&mut steady_kicks(128.0), 4.0, BLOCK_FRAMES)
+        };
+        let variation =
+            |values: &[f32]| -> f32 { values.windows(2).map(|w| (w[1] - w[0]).abs()).sum() };
+
+        let frames = run(EnvelopeSettings::new(10.0, 250.0, 0.0));
+        let raw: Vec<f32> = frames.iter().map(|f| f.metrics.loudness).collect();
+        let smoothed: Vec<f32> = frames
+            .iter()
+            .map(|f| f.metrics.envelopes.loudness.value)
+            .collect();
+        assert!(
+            variation(&smoothed) < 0.5 * variation(&raw),
+            "smoothed {:.2}, raw {:.2}",
+            variation(&smoothed),
+            variation(&raw)
+        );
+        for frame in &frames {
+            let envelope = frame.output.envelopes.loudness;
+            assert_eq!(envelope.peak, envelope.value, "at {:.3}", frame.time);
+        }
+
+        // Held longer than a beat, the peak never falls between kicks
+        let frames = run(EnvelopeSettings::new(10.0, 250.0, 1000.0));
+        let loudest = frames
+            .iter()
+            .map(|f| f.metrics.envelopes.loudness.value)
+            .fold(0.0, f32::max);
+        for frame in frames.iter().filter(|f| f.time > 1.0) {
+            let envelope = frame.metrics.envelopes.loudness;
+            assert!(envelope.peak >= envelope.value);
+            assert!(
+                envelope.peak > 0.9 * loudest,
+                "peak {:.3} fell at {:.3}",
+                envelope.peak,
+                frame.time
+            );
+        }
+    }
+
+    #[test]
+    fn envelopes_follow_the_stereo_field_and_centroid() {
+        let step_time = 1.5;
+        // Distinct attack and release everywhere, so a follower wired to
+        // the wrong settings lags by the wrong time
+        let config = AudioConfig {
+            hop_size: 256,
+            stereo_balance_envelope: EnvelopeSettings::new(200.0, 100.0, 0.0),
+            phase_correlation_envelope: EnvelopeSettings::new(50.0, 250.0, 0.0),
+            spectral_centroid_envelope: EnvelopeSettings::new(500.0, 20.0, 0.0),
+            ..Default::default()
+        };
+        let mut harness = OfflineHarness::new(config, SAMPLE_RATE, 2);
+        let sine = |frequency| {
+            SignalGenerator::new(SignalSettings {
+                kind: SignalKind::Sine,
+                sample_rate: SAMPLE_RATE,
+                frequency,
+                amplitude: 0.5,
+                ..Default::default()
+            })
+        };
+        let (mut low, mut high) = (sine(440.0), sine(2000.0));
+        // A centered 440 Hz tone, then a brighter one panned left with its
+        // right channel inverted: balance, correlation and centroid all step
+        let mut frames = Vec::new();
+        while harness.time() < step_time + 1.5 {
+            let after_step = harness.time() >= step_time;
+            let block: Vec<f32> = (0..BLOCK_FRAMES)
+                .flat_map(|_| {
+                    if after_step {
+                        let sample = high.next_sample();
+                        [sample, -0.25 * sample]
+                    } else {
+                        let sample = low.next_sample();
+                        [sample, sample]
+                    }
+                })
+                .collect();
+            frames.extend(harness.process_block(&block));
+        }
+
+        // Time the raw value crosses half its step, and the time the envelope
+        // covers 1 - 1/e of its own: one time constant later
+        let check = |name: &str,
+                     raw: fn(&AudioMetrics) -> f32,
+                     value: fn(&AudioMetrics) -> f32,
+                     time_constant_ms: f64| {
+            let before = frames.iter().rfind(|f| f.time < step_time).unwrap();
+            let after = &frames.last().unwrap().metrics;
+            let crossing = |values: fn(&AudioMetrics) -> f32, share: f32| {
+                let (from, to) = (values(&before.metrics), values(after));
+                frames
+                    .iter()
+                    .find(|f| {
+                        f.time > step_time && (values(&f.metrics) - from) / (to - from) >= share
+                    })
+                    .unwrap()
+                    .time
+            };
+            let lag = (crossing(value, 1.0 - (-1f32).exp()) - crossing(raw, 0.5)) * 1000.0;
+            assert!(
+                (lag - time_constant_ms).abs() < 0.25 * time_constant_ms,
+                "{name} lagged {lag:.0} ms, expected {time_constant_ms} ms"
+            );
+        };
+        // Balance and correlation fall, at their release; the centroid rises
+        check(
+            "balance",
+            |m| m.stereo_balance,
+            |m| m.envelopes.stereo_balance.value,
+            100.0,
+        );
+        check(
+            "correlation",
+            |m| m.phase_correlation,
+            |m| m.envelopes.phase_correlation.value,
+            250.0,
+        );
+        check(
+            "centroid",
+            |m| m.spectral_centroid,
+            |m| m.envelopes.spectral_centroid.value,
+            500.0,
+        );
+    }
+
+    #[test]
+    fn timbre_features_tell_tones_from_noise() {
+        let last_metrics = |kind| {
+            let settings = SignalSettings {
+                kind,
+                frequency: 440.0,
+                ..Default::default()
+            };
+            run_signal(settings, 1.0).pop().unwrap().metrics
+        };
+
+        let sine = last_metrics(SignalKind::Sine);
+        assert!((sine.spectral_centroid - 440.0).abs() < 5.0, "{sine:?}");
+        assert!(sine.spectral_spread < 100.0, "{sine:?}");
+        assert!(sine.spectral_rolloff < 500.0, "{sine:?}");
+        assert!(sine.spectral_flatness < 0.01, "{sine:?}");
+        // Two crossings per period
+        let expected_zcr = 2.0 * 440.0 / SAMPLE_RATE as f32;
+        assert!(
+            (sine.zero_crossing_rate - expected_zcr).abs() < 0.001,
+            "{sine:?}"
+        );
+        assert!((sine.crest_factor - 2f32.sqrt()).abs() < 0.01, "{sine:?}");
+
+        // Uniform white noise: flat up to Nyquist, a crest factor of √3
+        let white = last_metrics(SignalKind::WhiteNoise);
+        let nyquist = SAMPLE_RATE as f32 / 2.0;
+        assert!(
+            (white.spectral_centroid - nyquist / 2.0).abs() < 1000.0,
+            "{white:?}"
+        );
+        assert!(white.spectral_rolloff > 0.8 * nyquist, "{white:?}");
+        assert!(white.spectral_flatness > 0.5, "{white:?}");
+        assert!((white.zero_crossing_rate - 0.5).abs() < 0.05, "{white:?}");
+        assert!((white.crest_factor - 3f32.sqrt()).abs() < 0.1, "{white:?}");
+
+        let pink = last_metrics(SignalKind::PinkNoise);
+        assert!(pink.spectral_centroid < white.spectral_centroid);
+        assert!(
+            (sine.spectral_flatness..white.spectral_flatness).contains(&pink.spectral_flatness)
+        );
+    }
+
+    #[test]
+    fn drum_hits_are_classified_by_drum() {
+        let settings = SignalSettings {
+            kind: SignalKind::DrumLoop,
+            bpm: 120.0,
+            ..Default::default()
+        };
+        let beat_seconds = 60.0 / settings.bpm as f64;
+        let seconds = 8.0;
+        // Hits are reported once they are inside the analysis window
+        let window_seconds = AudioConfig::default().buffer_size as f64 / SAMPLE_RATE as f64;
+        let frames = run_signal(settings, seconds);
+        let hits: Vec<(Drum, f64, f32)> = frames
+            .iter()
+            .flat_map(|f| &f.output.events)
+            .filter_map(|event| match *event {
+                ControllerEvent::Hit {
+                    time,
+                    drum,
+                    velocity,
+                } => Some((drum, time, velocity)),
+                _ => None,
+            })
+            .collect();
+
+        // Kicks on every beat, snares on beats 2 and 4, hats on the offbeats
+        let pattern = |drum: Drum| {
+            (0..)
+                .map(|beat| match drum {
+                    Drum::Kick => beat as f64 * beat_seconds,
+                    Drum::Snare => (2 * beat + 1) as f64 * beat_seconds,
+                    Drum::Hat => (beat as f64 + 0.5) * beat_seconds,
+                })
+                .take_while(|&t| t <= seconds)
+                .collect::<Vec<f64>>()
+        };
+        for drum in Drum::ALL {
+            // The detector needs a few hits to learn each band's flux level
+            let times: Vec<f64> = hits
+                .iter()
+                .filter(|hit| hit.0 == drum && hit.1 >= 1.0)
+                .map(|hit| hit.1)
+                .collect();
+            let expected = pattern(drum);
+            for &hit in expected
+                .iter()
+                .filter(|&&t| (1.0..seconds - window_seconds).contains(&t))
+            {
+                assert!(
+                    times
+                        .iter()
+                        .any(|&t| (0.0..=window_seconds).contains(&(t - hit))),
+                    "missed {drum:?} at {hit:.3}s"
+                );
+            }
+            for &time in &times {
+                assert!(
+                    expected
+                        .iter()
+                        .any(|&hit| (0.0..=window_seconds).contains(&(time - hit))),
+                    "{drum:?} at {time:.3}s without a hit"
+                );
+            }
+        }
+        assert!(
+            hits.iter().all(|hit| hit.2 > 0.0 && hit.2 <= 1.0),
+            "{hits:?}"
+        );
+    }
+
+    #[test]
+    fn percussive_onsets_ignore_sustained_bass() {
+        let seconds = 8.0;
+        let bpm = 120.0;
+        let beat_seconds = 60.0 / bpm as f64;
+        let window_seconds = AudioConfig::default().buffer_size as f64 / SAMPLE_RATE as f64;
+        // Kicks under a heavy 45 Hz bass line that beats against their sub
+        let run = |config: AudioConfig| {
+            let mut harness = OfflineHarness::new(config, SAMPLE_RATE, 1);
+            let mut kicks = steady_kicks(bpm);
+            let mut bass = SignalGenerator::new(SignalSettings {
+                kind: SignalKind::Sine,
+                sample_rate: SAMPLE_RATE,
+                frequency: 45.0,
+                amplitude: 0.8,
+                ..Default::default()
+            });
+            let mut frames = Vec::new();
+            while harness.time() < seconds {
+                let block: Vec<f32> = (0..BLOCK_FRAMES)
+                    .map(|_| kicks.next_sample() + bass.next_sample())
+                    .collect();
+                frames.extend(harness.process_block(&block));
+            }
+            frames
+        };
+        // The detector needs a few kicks to learn the band's flux level
+        let sub_onsets = |frames: &[Frame]| -> Vec<f64> {
+            frames
+                .iter()
+                .flat_map(|f| &f.metrics.onsets)
+                .filter(|o| o.band.as_deref() == Some("sub") && o.time >= 1.0)
+                .map(|o| o.time)
+                .collect()
+        };
+        let kicks: Vec<f64> = (0..)
+            .map(|beat| beat as f64 * beat_seconds)
+            .take_while(|&t| t <= seconds)
+            .collect();
+
+        // Without separation the bass beating reads as a stream of onsets
+        let plain = run(AudioConfig::default());
+        let swamped = sub_onsets(&plain);
+        let counted_kicks = kicks.iter().filter(|&&t| t >= 1.0).count();
+        assert!(swamped.len() > 2 * counted_kicks, "{swamped:?}");
+        assert!(plain.iter().all(|f| f.metrics.percussive_energy == 0.0));
+
+        // Separation without routing leaves onsets alone
+        let separated = run(AudioConfig {
+            hpss_enabled: true,
+            percussive_onsets: false,
+            ..Default::default()
+        });
+        assert_eq!(render_onsets(&separated), render_onsets(&plain));
+        assert!(separated.iter().any(|f| f.metrics.percussive_energy > 0.0));
+
+        let routed = sub_onsets(&run(AudioConfig {
+            hpss_enabled: true,
+            ..Default::default()
+        }));
+        for &kick in kicks
+            .iter()
+            .filter(|&&t| (1.0..seconds - window_seconds).contains(&t))
+        {
+            assert!(
+                routed
+                    .iter()
+                    .any(|&t| (0.0..=window_seconds).contains(&(t - kick))),
+                "missed kick at {kick:.3}s"
+            );
+        }
+        for &time in &routed {
+            assert!(
+                kicks
+                    .iter()
+                    .any(|&kick| (0.0..=window_seconds).contains(&(time - kick))),
+                "onset at {time:.3}s without a kick"
+            );
+        }
+    }
+
+    #[test]
+    fn pre_filters_leave_the_loudness_meter_alone() {
+        let run = |config: AudioConfig| {
+            let mut harness = OfflineHarness::new(config, SAMPLE_RATE, 1);
+            let mut generator = SignalGenerator::new(SignalSettings {
+                kind: SignalKind::Sine,
+                sample_rate: SAMPLE_RATE,
+                frequency: 997.0,
+                amplitude: 0.25,
+                ..Default::default()
+            });
+            harness
+                .run_generator(&mut generator, 4.0, BLOCK_FRAMES)
+                .pop()
+                .unwrap()
+                .metrics
+        };
+
+        let plain = run(AudioConfig::default());
+        let cut = run(AudioConfig {
+            pre_filters: vec![FilterStage::new(FilterKind::HighPass, 100.0, 0.707, 0.0)],
+            pre_gain_db: -12.0,
+            ..Default::default()
+        });
+        // The analysis hears the gain, the meter doesn't
+        assert!(
+            cut.loudness < plain.loudness,
+            "{} {}",
+            cut.loudness,
+            plain.loudness
+        );
+        assert_eq!(cut.meter, plain.meter);
+    }
+
+    /// Offset of `time` from the nearest multiple of `period`.
+    fn wrap_seconds(time: f64, period: f64) -> f64 {
+        (time + period / 2.0).rem_euclid(period) - period / 2.0
+    }
+
+    #[test]
+    fn block_size_does_not_change_results() {
+        // A whole number of blocks of either size, so both feed the same audio
+        let seconds = (2 * 102_400) as f64 / SAMPLE_RATE as f64;
+        let small = run_signal_in_blocks(kick_pattern_settings(), seconds, 100);
+        let large = run_signal_in_blocks(kick_pattern_settings(), seconds, 4096);
+        assert!(!small.is_empty());
+        assert_eq!(small.len(), large.len());
+        assert_eq!(render_frames(&small), render_frames(&large));
+    }
+
+    #[test]
+    fn identical_runs_are_identical() {
+        let first = render_frames(&run_signal(kick_pattern_settings(), 4.0));
+        let second = render_frames(&run_signal(kick_pattern_settings(), 4.0));
+        assert_eq!(first, second);
+    }
+}
@@ -2,13 +2,15 @@ mod audio;
 mod config;
 mod controller;
 mod gui;
+#[cfg(test)]
+mod harness;
 mod visual;
 
 use audio::{AudioAnalyzer, AudioMetrics};
-use controller::{Controller, ControllerOutput};
+use controller::{Controller, ControllerEvent, ControllerOutput};
 use log::{debug, info};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex, RwLock};
+use std::sync::{Arc, Mutex, RwLock, mpsc};
 use std::thread;
 use std::time::Duration;
 
@@ -27,24 +29,36 @@ fn main() -> Result<(), eframe::Error> {
 
     // === Analyzer Setup ===
     let analyzer = Arc::new(Mutex::new(AudioAnalyzer::new(config.clone())));
+    let (metrics_tx, metrics_rx) = mpsc::channel::<AudioMetrics>();
+    let (event_tx, event_rx) = mpsc::channel::<ControllerEvent>();
 
     // === Analysis Thread ===
+    // Woken by the audio source whenever samples arrive; analyzes every full
+    // hop and forwards the metrics straight to the controller.
     debug!("Spawning analyzer thread...");
     let analyzer_thread = {
         let analyzer = analyzer.clone();
         let metrics = analyzer_metrics.clone();
-        let config = config.clone();
         let shutdown = shutdown.clone();
 
         thread::spawn(move || {
             debug!("Analyzer thread started");
-            while !shutdown.load(Ordering::Relaxed) {
-                let interval = config.read().unwrap().update_interval_ms;
-                thread::sleep(Duration::from_millis(interval));
-
-                if !shutdown.load(Ordering::Relaxed) {
-                    let new_metrics = analyzer.lock().unwrap().analyze();
-                    *metrics.write().unwrap() = new_metrics;
+            analyzer.lock().unwrap().wake_on_samples(thread::current());
+
+            'analysis: while !shutdown.load(Ordering::Relaxed) {
+                // Timeout only bounds how long a shutdown can go unnoticed
+                thread::park_timeout(Duration::from_millis(100));
+
+                loop {
+                    // Locked per hop only, so the GUI never waits on more than
+                    // one step of catching up
+                    let Some(new_metrics) = analyzer.lock().unwrap().next_hop() else {
+                        break;
+                    };
+                    *metrics.write().unwrap() = new_metrics.clone();
+                    if metrics_tx.send(new_metrics).is_err() {
+                        break 'analysis;
+                    }
                 }
             }
             debug!("Analyzer thread shutting down");
@@ -52,25 +66,23 @@ fn main() -> Result<(), eframe::Error> {
     };
 
     // === Controller Thread ===
+    // Runs once per metrics update, exits when the analyzer hangs up. Events
+    // are queued for the GUI rather than left in the output, which the next
+    // update overwrites before the GUI may have seen it.
     debug!("Spawning controller thread...");
     let controller_thread = {
-        let controller = Controller::new(config.clone());
-        let metrics = analyzer_metrics.clone();
+        let mut controller = Controller::new(config.clone());
         let output = controller_output.clone();
-        let config = config.clone();
-        let shutdown = shutdown.clone();
 
         thread::spawn(move || {
             debug!("Controller thread started");
-            while !shutdown.load(Ordering::Relaxed) {
-                let interval = config.read().unwrap().update_interval_ms;
-                thread::sleep(Duration::from_millis(interval));
-
-                if !shutdown.load(Ordering::Relaxed) {
-                    let current_metrics = metrics.read().unwrap().clone();
-                    let new_output = controller.process(current_metrics);
-                    *output.write().unwrap() = new_output;
+            for current_metrics in metrics_rx {
+                let mut new_output = controller.process(current_metrics);
+                for event in new_output.events.drain(..) {
+                    // The GUI hanging up only means nobody is watching anymore
+                    let _ = event_tx.send(event);
                 }
+                *output.write().unwrap() = new_output;
             }
             debug!("Controller thread shutting down");
         })
@@ -93,12 +105,14 @@ fn main() -> Result<(), eframe::Error> {
                 analyzer,
                 analyzer_metrics,
                 controller_output,
+                event_rx,
             )))
         }),
     );
 
     debug!("Signaling threads to shut down...");
     shutdown.store(true, Ordering::Relaxed);
+    analyzer_thread.thread().unpark();
 
     debug!("Waiting for analyzer thread to finish...");
     analyzer_thread
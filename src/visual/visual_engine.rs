@@ -1,18 +1,77 @@
-use crate::controller::ControllerOutput;
+use crate::audio::chroma::KeyMode;
+use crate::audio::{Drum, KeyEstimate};
+use crate::controller::{BEATS_PER_BAR, ControllerEvent, ControllerOutput, DropState};
 use eframe::egui;
 use std::sync::{Arc, RwLock};
 
+/// How fast onset flashes fade out, per second.
+const FLASH_DECAY: f32 = 8.0;
+
+/// Particles orbiting the pulse, scattered by noisy sounds.
+const PARTICLE_COUNT: usize = 48;
+
 pub struct VisualEngine {
     controller_output: Arc<RwLock<ControllerOutput>>,
+    /// Brightness of the overall onset flash, 0-1.
+    flash: f32,
+    /// Brightness of each band light's onset flash, 0-1.
+    band_flashes: Vec<f32>,
+    /// Brightness of the full-screen flash a drop starts with, 0-1.
+    drop_flash: f32,
+    /// Per drum, in [`Drum::ALL`] order: velocity of the latest hit, fading.
+    drum_flashes: [f32; 3],
 }
 
 impl VisualEngine {
     pub fn new(controller_output: Arc<RwLock<ControllerOutput>>) -> Self {
-        Self { controller_output }
+        Self {
+            controller_output,
+            flash: 0.0,
+            band_flashes: Vec::new(),
+            drop_flash: 0.0,
+            drum_flashes: [0.0; 3],
+        }
     }
 
-    pub fn render(&mut self, ctx: &egui::Context) {
-        // TODO: use controller_output to drive visuals
+    /// Fades the flashes and restarts the ones hit by new onsets and events.
+    fn update_flashes(&mut self, output: &ControllerOutput, events: &[ControllerEvent], dt: f32) {
+        let fade = (-FLASH_DECAY * dt).exp();
+        self.flash *= fade;
+        self.band_flashes.resize(output.band_energies.len(), 0.0);
+        for flash in &mut self.band_flashes {
+            *flash *= fade;
+        }
+
+        self.drop_flash *= fade;
+        for flash in &mut self.drum_flashes {
+            *flash *= fade;
+        }
+        for event in events {
+            match *event {
+                ControllerEvent::DropStarted { .. } => self.drop_flash = 1.0,
+                ControllerEvent::DropEnded { .. } | ControllerEvent::Beat { .. } => {}
+                ControllerEvent::Onset { band: None, .. } => self.flash = 1.0,
+                ControllerEvent::Onset {
+                    band: Some(idx), ..
+                } => {
+                    if let Some(flash) = self.band_flashes.get_mut(idx) {
+                        *flash = 1.0;
+                    }
+                }
+                ControllerEvent::Hit { drum, velocity, .. } => {
+                    let flash = &mut self.drum_flashes[drum as usize];
+                    *flash = flash.max(velocity);
+                }
+            }
+        }
+    }
+
+    /// Draws a frame, flashing on the controller `events` raised since the
+    /// previous one.
+    pub fn render(&mut self, ctx: &egui::Context, events: &[ControllerEvent]) {
+        let output = self.controller_output.read().unwrap().clone();
+        self.update_flashes(&output, events, ctx.input(|i| i.stable_dt));
+        let time = ctx.input(|i| i.time) as f32;
 
         egui::CentralPanel::default()
             .frame(
@@ -21,6 +80,187 @@ impl VisualEngine {
                     .inner_margin(0.0),
             )
             .show(ctx, |ui| {
+                let rect = ui.max_rect();
+
+                // Pulse stretches sideways with stereo width, grows with loudness
+                // and kicks outwards on every beat and kick drum; snares flash
+                // it white and hats light up the particles
+                let envelopes = &output.envelopes;
+                let [kick, snare, hat] = Drum::ALL.map(|drum| self.drum_flashes[drum as usize]);
+                let base = rect.height().min(rect.width()) * 0.25;
+                let beat_kick = (1.0 - output.tempo.phase).powi(3) * output.tempo.confidence;
+                let radius_y =
+                    base * (0.5 + envelopes.loudness.value) * (1.0 + 0.2 * beat_kick + 0.15 * kick);
+                let radius_x = radius_y * (1.0 + envelopes.stereo_width.value * 2.0);
+                let color = match output.drop_state {
+                    DropState::Drop | DropState::Sustain => egui::Color32::from_rgb(200, 40, 40),
+                    DropState::BuildUp => egui::Color32::from_rgb(180, 110, 40),
+                    DropState::Idle | DropState::Release => egui::Color32::from_rgb(40, 80, 160),
+                }
+                .lerp_to_gamma(color_temperature(output.brightness), 0.4)
+                .lerp_to_gamma(egui::Color32::WHITE, 0.5 * snare);
+
+                if self.drop_flash > 0.01 {
+                    ui.painter().rect_filled(
+                        rect,
+                        0.0,
+                        egui::Color32::WHITE.gamma_multiply(self.drop_flash * 0.5),
+                    );
+                }
+                ui.painter().add(egui::Shape::ellipse_filled(
+                    rect.center(),
+                    egui::vec2(radius_x, radius_y),
+                    color.gamma_multiply(0.6),
+                ));
+
+                // Particles orbit on the pulse's outline; noisy sounds throw
+                // them off it in a jittery swarm
+                for i in 0..PARTICLE_COUNT {
+                    let seed = i as f32 * 12.9898;
+                    let angle =
+                        std::f32::consts::TAU * i as f32 / PARTICLE_COUNT as f32 + time * 0.3;
+                    let jitter = (seed + time * 7.0).sin() * (seed * 0.37 + time * 11.0).cos();
+                    let scatter = 1.1 + output.noisiness * 0.5 * jitter;
+                    ui.painter().circle_filled(
+                        rect.center()
+                            + egui::vec2(angle.cos() * radius_x, angle.sin() * radius_y) * scatter,
+                        2.0 + 2.0 * output.noisiness + 2.0 * hat,
+                        color
+                            .lerp_to_gamma(egui::Color32::WHITE, hat)
+                            .gamma_multiply(0.8),
+                    );
+                }
+
+                // Thin ring marking the held loudness peak
+                if envelopes.loudness.peak > envelopes.loudness.value {
+                    let peak_scale =
+                        (0.5 + envelopes.loudness.peak) / (0.5 + envelopes.loudness.value);
+                    ui.painter().add(egui::Shape::ellipse_stroke(
+                        rect.center(),
+                        egui::vec2(radius_x, radius_y) * peak_scale,
+                        egui::Stroke::new(1.0, color),
+                    ));
+                }
+
+                // Ring around the pulse flashes on every onset
+                if self.flash > 0.01 {
+                    ui.painter().add(egui::Shape::ellipse_stroke(
+                        rect.center(),
+                        egui::vec2(radius_x, radius_y) * (1.0 + 0.1 * self.flash),
+                        egui::Stroke::new(4.0, egui::Color32::WHITE.gamma_multiply(self.flash)),
+                    ));
+                }
+
+                // Build-up tension: an outer ring closing in on the pulse
+                if output.buildup_intensity > 0.05 {
+                    let closing = 2.0 - output.buildup_intensity;
+                    ui.painter().add(egui::Shape::ellipse_stroke(
+                        rect.center(),
+                        egui::vec2(radius_x, radius_y) * closing,
+                        egui::Stroke::new(
+                            2.0 + 6.0 * output.buildup_intensity,
+                            egui::Color32::from_rgb(255, 140, 0)
+                                .gamma_multiply(output.buildup_intensity),
+                        ),
+                    ));
+                }
+
+                // Spectrum bars along the bottom edge, in the key's palette
+                let bar_color = output.key.map_or(color, key_color);
+                if !output.spectrum.is_empty() {
+                    let bar_width = rect.width() / output.spectrum.len() as f32;
+                    let max_height = rect.height() * 0.3;
+                    for (i, &level) in output.spectrum.iter().enumerate() {
+                        let left = rect.left() + i as f32 * bar_width;
+                        ui.painter().rect_filled(
+                            egui::Rect::from_min_max(
+                                egui::pos2(left, rect.bottom() - level * max_height),
+                                egui::pos2(left + bar_width * 0.8, rect.bottom()),
+                            ),
+                            0.0,
+                            bar_color.gamma_multiply(0.4),
+                        );
+                    }
+                }
+
+                // One "light" per energy band along the top edge
+                if !output.band_energies.is_empty() {
+                    let spacing = rect.width() / output.band_energies.len() as f32;
+                    let radius = (spacing * 0.25).min(20.0);
+                    for (i, (band, flash)) in output
+                        .band_energies
+                        .iter()
+                        .zip(&self.band_flashes)
+                        .enumerate()
+                    {
+                        let center = egui::pos2(
+                            rect.left() + spacing * (i as f32 + 0.5),
+                            rect.top() + radius * 2.0,
+                        );
+                        ui.painter().circle_filled(
+                            center,
+                            radius,
+                            egui::Color32::WHITE.gamma_multiply(band.energy),
+                        );
+                        if *flash > 0.01 {
+                            ui.painter().circle_stroke(
+                                center,
+                                radius * 1.3,
+                                egui::Stroke::new(
+                                    2.0,
+                                    egui::Color32::YELLOW.gamma_multiply(*flash),
+                                ),
+                            );
+                        }
+                    }
+                }
+
+                // Melody marker on the right edge, rising with the lead's pitch
+                // from C2 to C7
+                if let Some(pitch) = output.pitch {
+                    let height = ((pitch.midi_note - 36.0) / 60.0).clamp(0.0, 1.0);
+                    ui.painter().circle_filled(
+                        egui::pos2(
+                            rect.right() - 24.0,
+                            rect.bottom() - rect.height() * (0.1 + 0.8 * height),
+                        ),
+                        4.0 + 6.0 * pitch.confidence,
+                        egui::Color32::WHITE.gamma_multiply(pitch.confidence),
+                    );
+                }
+
+                // Beat-in-bar dots in the bottom left corner, first beat larger
+                for beat in 1..=BEATS_PER_BAR {
+                    let radius = if beat == 1 { 7.0 } else { 5.0 };
+                    let center = rect.left_bottom() + egui::vec2(20.0 * beat as f32, -16.0);
+                    let lit = beat == output.bar_position.beat_in_bar;
+                    ui.painter().circle_filled(
+                        center,
+                        radius,
+                        egui::Color32::WHITE.gamma_multiply(if lit { 0.9 } else { 0.15 }),
+                    );
+                }
+
+                if output.tempo.bpm > 0.0 {
+                    ui.painter().text(
+                        rect.right_bottom() - egui::vec2(12.0, 12.0),
+                        egui::Align2::RIGHT_BOTTOM,
+                        format!("{:.0} BPM", output.tempo.bpm),
+                        egui::FontId::monospace(14.0),
+                        egui::Color32::WHITE.gamma_multiply(0.3 + 0.7 * beat_kick),
+                    );
+                }
+
+                if let Some(seconds) = output.time_to_drop {
+                    ui.painter().text(
+                        rect.center_bottom() - egui::vec2(0.0, 12.0),
+                        egui::Align2::CENTER_BOTTOM,
+                        format!("DROP IN {seconds:.1}"),
+                        egui::FontId::monospace(18.0),
+                        egui::Color32::from_rgb(255, 140, 0),
+                    );
+                }
+
                 ui.centered_and_justified(|ui| {
                     ui.colored_label(
                         egui::Color32::WHITE,
@@ -32,3 +272,20 @@ impl VisualEngine {
         ctx.request_repaint();
     }
 }
+
+/// Hue from the key's place on the circle of fifths, so related keys get
+/// related colors; minor keys are darker. Fades to gray as confidence drops.
+fn key_color(estimate: KeyEstimate) -> egui::Color32 {
+    let hue = estimate.key.fifths_position() as f32 / 12.0;
+    let value = match estimate.key.mode {
+        KeyMode::Major => 1.0,
+        KeyMode::Minor => 0.7,
+    };
+    egui::ecolor::Hsva::new(hue, 0.8 * estimate.confidence, value, 1.0).into()
+}
+
+/// Warm amber for dark sounds (0), cool blue for bright ones (1).
+fn color_temperature(brightness: f32) -> egui::Color32 {
+    egui::Color32::from_rgb(255, 140, 50)
+        .lerp_to_gamma(egui::Color32::from_rgb(140, 190, 255), brightness)
+}